@@ -1,27 +1,220 @@
 mod service;
 mod providers;
 mod debouncer;
+mod notify;
+mod record;
+mod logging;
+mod metrics;
 
+pub use providers::{detect as detect_provider, WindowProvider, DETECT_ENV_VARS};
+pub use debouncer::Debouncer;
+
+use crate::types::*;
+use std::path::PathBuf;
+use std::str::FromStr;
 use anyhow::Result;
+use colored::Colorize;
+use regex::Regex;
+use strum::VariantNames;
+
+/// Window types that never become the active window by default
+const DEFAULT_IGNORE_TYPES: [WindowType; 7] = [
+	WindowType::Dock,
+	WindowType::Menu,
+	WindowType::DropdownMenu,
+	WindowType::PopupMenu,
+	WindowType::Tooltip,
+	WindowType::Notification,
+	WindowType::Splash,
+];
+
+const DEFAULT_INTERVAL: u64 = 1000;
+const DEFAULT_MAX_RESTARTS: u32 = 5;
+const DEFAULT_MOVE_DEBOUNCE: u64 = 15;
 
 #[derive(clap::Args, Clone, Debug)]
 pub struct Args {
 	/// Specify the window provider instead of auto-detecting
 	#[arg(short, long, value_enum)]
 	provider: Option<providers::WindowProvider>,
+
+	/// How to determine the display of a window that spans multiple monitors
+	#[arg(long, value_enum, default_value_t = providers::DisplayMode::default())]
+	display_mode: providers::DisplayMode,
+
+	/// Window snapshot file for the mock provider or recording for the replay provider, or "-" for stdin
+	#[arg(long, value_name = "PATH")]
+	source: Option<PathBuf>,
+
+	/// Record window changes to a JSONL file, which can be played back with the replay provider
+	#[arg(long, value_name = "PATH")]
+	record: Option<PathBuf>,
+
+	/// Periodically write metrics in the Prometheus text format, e.g. for the node_exporter textfile collector
+	#[arg(long, value_name = "PATH")]
+	metrics_file: Option<PathBuf>,
+
+	/// Interval between window snapshots read from a file (in milliseconds)
+	#[arg(long, value_name = "MS", default_value_t = DEFAULT_INTERVAL)]
+	interval: u64,
+
+	/// Serve on the system bus instead of the session bus, e.g. for kiosks without a user session (requires a bus policy)
+	#[arg(long)]
+	system_bus: bool,
+
+	/// Allow clients to override the current windows with `wctx set`
+	#[arg(long)]
+	allow_override: bool,
+
+	/// Number of times to restart the window provider after consecutive failures before exiting
+	#[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_RESTARTS)]
+	max_restarts: u32,
+
+	/// Log level for the systemd journal, or stderr when not running as a service [default: info for the journal, otherwise off]
+	#[arg(long, value_enum, value_name = "LEVEL")]
+	log_level: Option<logging::LogLevel>,
+
+	/// Only track the active window, leaving the pointer window empty (reduces overhead if it's never queried)
+	#[arg(long)]
+	active_only: bool,
+
+	/// Also check the pointer window every MS milliseconds in the X11 provider, for window managers that don't send enter events for every window (0 to disable)
+	#[arg(long, value_name = "MS", default_value_t = 0)]
+	poll_pointer: u64,
+
+	/// How long window moves and resizes are debounced in the X11 provider before the final geometry is reported (0 to report every change)
+	#[arg(long, value_name = "MS", default_value_t = DEFAULT_MOVE_DEBOUNCE)]
+	move_debounce: u64,
+
+	/// Keep the last active window when focus moves to no window (e.g. when clicking the desktop)
+	#[arg(long)]
+	sticky_active: bool,
+
+	/// Window types that never become the active window, keeping the last application window active
+	#[arg(long, value_name = "TYPES", value_delimiter = ',', value_parser = parse_window_type, default_values_t = DEFAULT_IGNORE_TYPES)]
+	ignore_types: Vec<WindowType>,
+
+	/// Use the title set by the application instead of the one displayed by the window manager (`_NET_WM_VISIBLE_NAME`) in the X11 provider
+	#[arg(long)]
+	no_visible_name: bool,
+
+	/// XInput2 seat to resolve the active and pointer windows with in the X11 provider, named after its master devices (e.g. "seat1" for "seat1 pointer") [default: the core devices]
+	#[arg(long, value_name = "NAME")]
+	seat: Option<String>,
+
+	/// Name displays by their RandR output connector (e.g. HDMI-A-1, as listed by `xrandr --query`) instead of the monitor name in the X11 provider
+	#[arg(long)]
+	connector_names: bool,
+
+	/// Track override-redirect windows (e.g. menus and tooltips) in the X11 provider as the `override` window type
+	#[arg(long)]
+	track_override: bool,
+
+	/// Regex for window classes that never become the active or pointer window (can be repeated)
+	#[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+	ignore_class: Vec<Regex>,
+}
+
+fn parse_window_type(value: &str) -> Result<WindowType, String> {
+	// accept any casing with or without separators, e.g. "popupmenu", "popup-menu" or "POPUP_MENU"
+	let normalized = value.replace(['-', '_'], "").to_uppercase();
+
+	WindowType::VARIANTS.iter()
+		.find(|v| v.replace('_', "") == normalized)
+		.and_then(|v| WindowType::from_str(v).ok())
+		.ok_or_else(|| format!("expected one of: {}", WindowType::VARIANTS[1..].join(", ").to_lowercase()))
+}
+
+impl Default for Args {
+	fn default() -> Self {
+		Self {
+			provider: None,
+			display_mode: Default::default(),
+			source: None,
+			record: None,
+			metrics_file: None,
+			interval: DEFAULT_INTERVAL,
+			system_bus: false,
+			allow_override: false,
+			max_restarts: DEFAULT_MAX_RESTARTS,
+			log_level: None,
+			active_only: false,
+			poll_pointer: 0,
+			move_debounce: DEFAULT_MOVE_DEBOUNCE,
+			sticky_active: false,
+			ignore_types: DEFAULT_IGNORE_TYPES.to_vec(),
+			no_visible_name: false,
+			seat: None,
+			connector_names: false,
+			track_override: false,
+			ignore_class: Vec::new(),
+		}
+	}
 }
 
 pub async fn run(args: Args) -> Result<()> {
+	logging::init(args.log_level);
+
 	// use a channel to signal when the dbus service is ready and send a proxy client to providers
 	let (tx, rx) = tokio::sync::oneshot::channel();
+	// signals when the provider has determined the initial windows
+	let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+
+	let recorder = args.record.as_deref().map(record::Recorder::create).transpose()?;
+
+	if let Some(path) = args.metrics_file.clone() {
+		let system_bus = args.system_bus;
+		let provider = args.provider.or_else(detect_provider).map(|p| p.to_string().to_lowercase()).unwrap_or_default();
 
-	let service_task = tokio::spawn(service::serve(tx));
-	let provider_task = tokio::spawn(providers::serve(args.provider, rx));
+		tokio::spawn(async move {
+			if let Err(err) = metrics::serve(path, provider, system_bus).await {
+				tracing::error!(error = %err, "Failed to export metrics");
+				eprintln!("{} {}", "Failed to export metrics:".bright_red(), err);
+			}
+		});
+	}
+
+	let options = service::ServiceOptions::new(&args, recorder);
+	let system_bus = args.system_bus;
+
+	let service_task = tokio::spawn(service::serve(tx, ready_tx, options));
+	let provider_task = tokio::spawn(providers::serve(args, rx));
+
+	tokio::spawn(async move {
+		if let Err(err) = notify::serve(ready_rx, system_bus).await {
+			tracing::error!(error = %err, "Failed to notify systemd");
+			eprintln!("{} {}", "Failed to notify systemd:".bright_red(), err);
+		}
+	});
 
 	let result = tokio::select! {
         res = service_task => res?,
         res = provider_task => res?,
     };
 
+	if let Err(err) = &result {
+		tracing::error!(error = %err, "Daemon stopped");
+	}
+
 	result
 }
+
+/// Run the window provider inline without the D-Bus service, sending window changes through `tx`
+pub async fn standalone(tx: providers::WindowSender) -> Result<()> {
+	providers::standalone(Args::default(), tx).await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use clap::FromArgMatches;
+
+	#[test]
+	fn default_args_match_clap_defaults() {
+		let command = <Args as clap::Args>::augment_args(clap::Command::new("daemon"));
+		let parsed = Args::from_arg_matches(&command.get_matches_from(["daemon"])).unwrap();
+
+		// `Regex` has no `PartialEq`, so compare the debug output
+		assert_eq!(format!("{:?}", Args::default()), format!("{:?}", parsed));
+	}
+}
@@ -5,6 +5,8 @@ use tokio::time::{sleep, Instant, Duration};
 /// will yield the most-recent value only after no new value has arrived
 /// for at least `delay` duration. After yielding, it “resets” and can be awaited
 /// again for a new debounced value.
+///
+/// Optionally, it can also yield the first value of each burst immediately (leading edge).
 pub struct Debouncer<T> {
 	/// Used to push new values.
 	input_tx: mpsc::UnboundedSender<T>,
@@ -16,6 +18,13 @@ impl<T: Send + 'static> Debouncer<T> {
 	/// Create a new debouncer. The output future resolves only after no new value
 	/// is pushed for the specified `delay` period.
 	pub fn new(delay: Duration) -> Self {
+		Self::new_with(delay, false)
+	}
+
+	/// Create a new debouncer, optionally yielding the first value of a burst right away.
+	/// When `leading` is true, the rest of the burst is still debounced as usual, but
+	/// a trailing value is only yielded if more values arrived after the first one.
+	pub fn new_with(delay: Duration, leading: bool) -> Self {
 		// Create an unbounded channel for incoming events.
 		let (input_tx, mut input_rx) = mpsc::unbounded_channel();
 		// Create an unbounded channel to send out debounced events.
@@ -25,8 +34,14 @@ impl<T: Send + 'static> Debouncer<T> {
 		tokio::spawn(async move {
 			// Loop as long as new input values keep coming.
 			while let Some(first) = input_rx.recv().await {
-				// Start a debounce cycle with the first value.
-				let mut last = first;
+				// Start a debounce cycle with the first value…
+				let mut last = Some(first);
+
+				// …sending it out immediately for leading-edge debouncing.
+				if leading {
+					let _ = output_tx.send(last.take().unwrap());
+				}
+
 				// Create a timer that will fire after the specified delay.
 				let timer = sleep(delay);
 				// Pin the timer so that we can call `.reset()`.
@@ -38,7 +53,7 @@ impl<T: Send + 'static> Debouncer<T> {
                         maybe = input_rx.recv() => {
                             if let Some(new_val) = maybe {
                                 // Update our “latest” value…
-                                last = new_val;
+                                last = Some(new_val);
                                 // …and reset the timer to fire delay from now.
                                 timer.as_mut().reset(Instant::now() + delay);
                             } else {
@@ -53,9 +68,11 @@ impl<T: Send + 'static> Debouncer<T> {
                     }
 				}
 
-				// Send out the most recent value.
+				// Send out the most recent value, if it wasn't already sent on the leading edge.
 				// (We ignore errors here because it just means no one is awaiting.)
-				let _ = output_tx.send(last);
+				if let Some(last) = last {
+					let _ = output_tx.send(last);
+				}
 			}
 		});
 
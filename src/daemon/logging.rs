@@ -0,0 +1,47 @@
+use std::env;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+#[derive(Copy, Clone, Debug, clap::ValueEnum)]
+pub enum LogLevel {
+	Off,
+	Error,
+	Warn,
+	Info,
+	Debug,
+	Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+	fn from(level: LogLevel) -> Self {
+		match level {
+			LogLevel::Off => LevelFilter::OFF,
+			LogLevel::Error => LevelFilter::ERROR,
+			LogLevel::Warn => LevelFilter::WARN,
+			LogLevel::Info => LevelFilter::INFO,
+			LogLevel::Debug => LevelFilter::DEBUG,
+			LogLevel::Trace => LevelFilter::TRACE,
+		}
+	}
+}
+
+/// Send structured logs to the systemd journal when running as a service, or to stderr otherwise.
+/// Interactive runs already print friendly colored messages, so they don't log unless a level is given.
+pub fn init(level: Option<LogLevel>) {
+	// systemd sets this when stdout/stderr are connected to the journal
+	let journal = env::var_os("JOURNAL_STREAM").and_then(|_| tracing_journald::layer().ok());
+
+	let level = match (level, &journal) {
+		(Some(level), _) => level,
+		(None, Some(_)) => LogLevel::Info,
+		(None, None) => LogLevel::Off,
+	};
+
+	let stderr = journal.is_none().then(|| tracing_subscriber::fmt::layer().with_writer(std::io::stderr));
+
+	tracing_subscriber::registry()
+		.with(LevelFilter::from(level))
+		.with(journal)
+		.with(stderr)
+		.init();
+}
@@ -0,0 +1,90 @@
+use crate::types::*;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use tokio::time::{interval, Duration};
+use zbus::{Connection, proxy};
+
+/// How often to write the metrics file
+const METRICS_INTERVAL: Duration = Duration::from_secs(15);
+
+#[proxy(
+	interface = "org.wctx.Windows",
+	default_service = "org.wctx",
+	default_path = "/"
+)]
+trait Windows {
+	#[zbus(property)]
+	fn active_window(&self) -> zbus::Result<DictMap>;
+
+	#[zbus(property)]
+	fn pointer_window(&self) -> zbus::Result<DictMap>;
+
+	#[zbus(property)]
+	fn last_changed(&self) -> zbus::Result<u64>;
+
+	#[zbus(property)]
+	fn focus_changes(&self) -> zbus::Result<u64>;
+}
+
+/// Periodically write the daemon's counters in the Prometheus text format, for the node_exporter textfile collector
+pub async fn serve(path: PathBuf, provider: String, system_bus: bool) -> Result<()> {
+	let connection = if system_bus { Connection::system().await? } else { Connection::session().await? };
+	let windows = WindowsProxy::builder(&connection)
+		// properties are read on every tick, so there's no point caching them
+		.cache_properties(zbus::proxy::CacheProperties::No)
+		.build().await?;
+
+	let mut ticker = interval(METRICS_INTERVAL);
+
+	loop {
+		ticker.tick().await;
+
+		// the service may not be up yet on the first tick
+		let Ok(metrics) = read_metrics(&windows, &provider).await else {
+			continue;
+		};
+
+		if let Err(err) = write_atomic(&path, &metrics).await {
+			tracing::warn!(path = %path.display(), error = %err, "Failed to write metrics");
+		}
+	}
+}
+
+async fn read_metrics(windows: &WindowsProxy<'_>, provider: &str) -> Result<String> {
+	let active = WindowDict::try_from(windows.active_window().await?)?;
+	let pointer = WindowDict::try_from(windows.pointer_window().await?)?;
+	let tracked = |window: &WindowDict| u8::from(!window.id.is_empty());
+
+	let mut out = String::new();
+
+	writeln!(out, "# HELP wctx_focus_changes_total Number of times the active window changed to a different window.")?;
+	writeln!(out, "# TYPE wctx_focus_changes_total counter")?;
+	writeln!(out, "wctx_focus_changes_total {}", windows.focus_changes().await?)?;
+
+	writeln!(out, "# HELP wctx_last_change_timestamp_seconds When the active or pointer window last changed.")?;
+	writeln!(out, "# TYPE wctx_last_change_timestamp_seconds gauge")?;
+	writeln!(out, "wctx_last_change_timestamp_seconds {}", windows.last_changed().await? as f64 / 1000.0)?;
+
+	writeln!(out, "# HELP wctx_windows_tracked Whether there is currently an active or pointer window.")?;
+	writeln!(out, "# TYPE wctx_windows_tracked gauge")?;
+	writeln!(out, "wctx_windows_tracked{{context=\"active\"}} {}", tracked(&active))?;
+	writeln!(out, "wctx_windows_tracked{{context=\"pointer\"}} {}", tracked(&pointer))?;
+
+	writeln!(out, "# HELP wctx_provider_info The window provider used by the daemon.")?;
+	writeln!(out, "# TYPE wctx_provider_info gauge")?;
+	writeln!(out, "wctx_provider_info{{provider=\"{provider}\"}} 1")?;
+
+	Ok(out)
+}
+
+/// Write through a temporary file, so the collector never reads a partially written file
+async fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+	let mut tmp = path.as_os_str().to_owned();
+	tmp.push(".tmp");
+
+	tokio::fs::write(&tmp, contents).await?;
+	tokio::fs::rename(&tmp, path).await?;
+
+	Ok(())
+}
@@ -0,0 +1,78 @@
+use std::env;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use anyhow::Result;
+use tokio::sync::oneshot::Receiver;
+use tokio::time::{interval, timeout, Duration};
+use zbus::{fdo::PeerProxy, Connection};
+
+/// How long to wait for the provider to determine the initial windows before reporting ready anyway
+const READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Report readiness to systemd and keep its watchdog fed, when running as a `Type=notify` unit
+pub async fn serve(ready: Receiver<()>, system_bus: bool) -> Result<()> {
+	if env::var_os("NOTIFY_SOCKET").is_none() {
+		return Ok(());
+	}
+
+	let _ = timeout(READY_TIMEOUT, ready).await;
+	sd_notify("READY=1")?;
+
+	let Some(watchdog) = watchdog_interval() else {
+		return Ok(());
+	};
+
+	// the watchdog is only fed while the service answers over the bus, so a hung service gets restarted
+	let connection = if system_bus { Connection::system().await? } else { Connection::session().await? };
+	let service = PeerProxy::builder(&connection)
+		.destination("org.wctx")?
+		.path("/")?
+		.build().await?;
+
+	// ping at half the interval so a single late tick doesn't trip the watchdog
+	let mut ticker = interval(watchdog / 2);
+
+	loop {
+		ticker.tick().await;
+
+		match timeout(watchdog / 2, service.ping()).await {
+			Ok(Ok(())) => sd_notify("WATCHDOG=1")?,
+			Ok(Err(err)) => tracing::warn!(error = %err, "Daemon service didn't answer the watchdog ping"),
+			Err(_) => tracing::warn!("Daemon service timed out answering the watchdog ping"),
+		}
+	}
+}
+
+fn watchdog_interval() -> Option<Duration> {
+	// the watchdog applies to the main process only, unless WATCHDOG_PID says otherwise
+	if let Ok(pid) = env::var("WATCHDOG_PID") {
+		if pid.parse::<u32>().ok()? != std::process::id() {
+			return None;
+		}
+	}
+
+	let usec = env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+
+	match usec {
+		0 => None,
+		usec => Some(Duration::from_micros(usec)),
+	}
+}
+
+/// Minimal implementation of `sd_notify(3)`
+fn sd_notify(state: &str) -> Result<()> {
+	let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+		return Ok(());
+	};
+
+	// a leading "@" denotes a socket in the abstract namespace
+	let addr = match path.as_encoded_bytes().strip_prefix(b"@") {
+		Some(name) => SocketAddr::from_abstract_name(name)?,
+		None => SocketAddr::from_pathname(&path)?,
+	};
+
+	let socket = UnixDatagram::unbound()?;
+	socket.send_to_addr(state.as_bytes(), &addr)?;
+
+	Ok(())
+}
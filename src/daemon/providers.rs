@@ -1,15 +1,21 @@
 mod x11;
 mod kwin;
 mod gnome;
+mod mock;
+mod river;
+mod wayfire;
 
 use crate::types::*;
+use std::path::{Path, PathBuf};
+use super::Args;
 use super::service::ServiceProxy;
 use anyhow::{anyhow, Result};
 use colored::Colorize;
 use strum::VariantNames;
 use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot::Receiver;
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, Duration, Instant};
 
 #[derive(Copy, Clone, Debug, clap::ValueEnum, strum::Display, strum::VariantNames)]
 #[clap(rename_all = "lowercase")]
@@ -17,40 +23,249 @@ pub enum WindowProvider {
 	X11,
 	KWin,
 	GNOME,
+	River,
+	Wayfire,
+	/// Reads window snapshots from a file or stdin (for testing and scripting)
+	Mock,
+	/// Plays back window changes recorded with `--record` (for reproducing issues)
+	Replay,
 }
 
-pub async fn serve(provider: Option<WindowProvider>, rx: Receiver<ServiceProxy<'_>>) -> Result<()> {
-	let Some(provider) = provider
-		.or_else(x11::detect)
+impl WindowProvider {
+	/// Whether the provider always sets the pointer window, so readiness waits for it
+	pub fn tracks_pointer(self) -> bool {
+		match self {
+			Self::X11 | Self::KWin | Self::GNOME => true,
+			Self::River | Self::Wayfire => false,
+			// snapshots and recordings may only have active windows
+			Self::Mock | Self::Replay => false,
+		}
+	}
+
+	/// Bus name of the compositor running the provider's script or extension, for providers that set windows from outside the daemon
+	pub fn host_bus_name(self) -> Option<&'static str> {
+		match self {
+			Self::KWin => Some("org.kde.KWin"),
+			Self::GNOME => Some("org.gnome.Shell"),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, Default, clap::ValueEnum, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum DisplayMode {
+	/// The display containing the center of the window, or the one it overlaps most
+	#[default]
+	Center,
+	/// The display the window overlaps most
+	#[value(alias = "primary")]
+	Overlap,
+	/// All displays the window overlaps, comma-separated
+	All,
+}
+
+/// Delay before the first restart of a failed provider, doubled for each consecutive failure
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+/// How long a provider must run before its failures are no longer considered consecutive
+const RESTART_RESET_AFTER: Duration = Duration::from_secs(60);
+
+pub type WindowSender = UnboundedSender<(WindowContext, WindowDict)>;
+
+/// Destination for window changes from a provider
+pub enum WindowSink<'a> {
+	/// Publish to the D-Bus service
+	Service(&'a ServiceProxy<'a>),
+	/// Send directly to an in-process receiver (standalone mode)
+	Channel(WindowSender),
+}
+
+impl WindowSink<'_> {
+	pub async fn set_window(&self, context: WindowContext, window: WindowDict) -> Result<()> {
+		match self {
+			Self::Service(service) => service.windows.set_window(context, window.into()).await.map_err(Into::into),
+			Self::Channel(tx) => tx.send((context, window)).map_err(|_| anyhow!("Window receiver was closed")),
+		}
+	}
+	/// Update properties of a window, all in one call if there are several (e.g. the geometry and display)
+	pub async fn update_window(&self, context: WindowContext, mut props: Vec<(WindowProp, String)>, window: impl FnOnce() -> WindowDict) -> Result<()> {
+		match self {
+			Self::Service(service) if props.len() == 1 => {
+				let (key, value) = props.remove(0);
+				service.windows.update_window(context, key, &value).await.map_err(Into::into)
+			},
+			Self::Service(service) => service.windows.update_window_props(context, props).await.map_err(Into::into),
+			// in-process receivers only take whole windows
+			Self::Channel(tx) => tx.send((context, window())).map_err(|_| anyhow!("Window receiver was closed")),
+		}
+	}
+	pub async fn set_displays(&self, displays: Vec<DisplayDict>) -> Result<()> {
+		match self {
+			Self::Service(service) => service.displays.set_displays(displays.into_iter().map(Into::into).collect()).await.map_err(Into::into),
+			Self::Channel(_) => Ok(()),
+		}
+	}
+}
+
+/// Environment variables used to detect the window provider
+pub const DETECT_ENV_VARS: [&str; 6] = [
+	"XDG_SESSION_TYPE",
+	"XDG_SESSION_DESKTOP",
+	"XDG_CURRENT_DESKTOP",
+	"KDE_SESSION_VERSION",
+	"WAYFIRE_SOCKET",
+	"WAYLAND_DISPLAY",
+];
+
+/// Detect the window provider for the current session
+pub fn detect() -> Option<WindowProvider> {
+	x11::detect()
 		.or_else(kwin::detect)
 		.or_else(gnome::detect)
-		else {
-			eprintln!(
-				"{} No supported window provider detected. Currently supports: {}\n\n{}\n{}",
-				"Error:".bright_red().bold(),
-				WindowProvider::VARIANTS.join(", "),
-				"If you would like to help get support added for your desktop, please feel free to post, comment or contribute:".bright_yellow(),
-				"https://github.com/slightlyfaulty/wctx/issues"
-			);
-			std::process::exit(126);
-		};
+		.or_else(wayfire::detect)
+		.or_else(river::detect)
+}
 
+pub async fn serve(args: Args, rx: Receiver<ServiceProxy<'_>>) -> Result<()> {
+	let Some(provider) = args.provider.or_else(detect) else {
+		tracing::error!(
+			session_type = %std::env::var("XDG_SESSION_TYPE").unwrap_or_default(),
+			current_desktop = %std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default(),
+			"No supported window provider detected",
+		);
+
+		return Err(ExitError::new(126, format!(
+			"No supported window provider detected. Currently supports: {}\nRun \"{}\" to see the environment used for detection.\n\n{}\n{}",
+			WindowProvider::VARIANTS.join(", "),
+			"wctx providers".bright_yellow().bold(),
+			"If you would like to help get support added for your desktop, please feel free to post, comment or contribute:".bright_yellow(),
+			"https://github.com/slightlyfaulty/wctx/issues"
+		)).into());
+	};
+
+	tracing::info!(provider = %provider, detected = args.provider.is_none(), "Using window provider");
 	println!("{} {}", "Using window provider:".bright_blue(), provider.to_string().white().bold());
 
 	// wait for dbus to be ready and get a service proxy for providers that need it
 	let service = rx.await?;
 
-	let result = match provider {
-		WindowProvider::X11 => x11::serve(&service).await,
+	let mut failures = 0;
+
+	loop {
+		let started = Instant::now();
+		let result = run_provider(provider, &service, &args).await;
+
+		let Err(err) = result else {
+			return Ok(());
+		};
+
+		tracing::error!(provider = %provider, error = %err, "Window provider failed");
+		eprintln!("{}", format!("Window provider {} failed: {}", provider.to_string().bold(), err).bright_red());
+
+		// a provider that ran for a while before failing isn't failing repeatedly
+		if started.elapsed() >= RESTART_RESET_AFTER {
+			failures = 0;
+		}
+
+		failures += 1;
+
+		if failures > args.max_restarts {
+			return Err(err);
+		}
+
+		let delay = RESTART_DELAY.saturating_mul(1 << (failures - 1).min(16)).min(MAX_RESTART_DELAY);
+
+		tracing::warn!(provider = %provider, attempt = failures, delay_secs = delay.as_secs(), "Restarting window provider");
+		eprintln!("{} Restarting in {}s (attempt {}/{})", "Warning:".bright_yellow().bold(), delay.as_secs(), failures, args.max_restarts);
+
+		let _ = service.application.set_status(&format!(
+			"Window provider {} failed, restarting in {}s (attempt {}/{}): {}",
+			provider, delay.as_secs(), failures, args.max_restarts, err,
+		)).await;
+
+		sleep(delay).await;
+
+		let _ = service.application.set_status("").await;
+	}
+}
+
+async fn run_provider(provider: WindowProvider, service: &ServiceProxy<'_>, args: &Args) -> Result<()> {
+	match provider {
+		WindowProvider::X11 => x11::serve(WindowSink::Service(service), args).await,
 		WindowProvider::KWin => kwin::serve().await,
-		WindowProvider::GNOME => gnome::serve(&service).await,
-	};
+		WindowProvider::GNOME => gnome::serve(service).await,
+		WindowProvider::River => river::serve(service).await,
+		WindowProvider::Wayfire => wayfire::serve(service).await,
+		WindowProvider::Mock => mock::serve(service, args).await,
+		WindowProvider::Replay => mock::replay(service, args).await,
+	}
+}
+
+pub async fn standalone(args: Args, tx: WindowSender) -> Result<()> {
+	if x11::detect().is_none() {
+		return Err(anyhow!("Standalone mode is currently only supported by the {} window provider", WindowProvider::X11.to_string().bold()));
+	}
+
+	x11::serve(WindowSink::Channel(tx), &args).await
+}
+
+/// Get the short process name of a local process
+pub fn process_comm(pid: u32) -> Option<String> {
+	if pid == 0 {
+		return None;
+	}
+
+	let comm = std::fs::read_to_string(format!("/proc/{pid}/comm")).ok()?;
+
+	Some(comm.trim_end().to_string())
+}
+
+/// Get the icon name from the desktop entry matching the first of the given application ids
+/// (e.g. a desktop file hint, then the window class), falling back to the first non-empty id
+pub fn desktop_icon(ids: &[&str]) -> String {
+	let dirs = applications_dirs();
+
+	for id in ids.iter().filter(|id| !id.is_empty()) {
+		let id = id.strip_suffix(".desktop").unwrap_or(id);
+
+		// window classes are often capitalized versions of the desktop file name
+		for id in [id.to_string(), id.to_lowercase()] {
+			for dir in &dirs {
+				if let Some(icon) = read_desktop_icon(&dir.join(format!("{id}.desktop"))) {
+					return icon;
+				}
+			}
+		}
+	}
+
+	ids.iter().find(|id| !id.is_empty()).map(|id| id.to_string()).unwrap_or_default()
+}
+
+/// Get the directories containing desktop entries, in order of precedence
+fn applications_dirs() -> Vec<PathBuf> {
+	let data_dirs = std::env::var("XDG_DATA_DIRS").unwrap_or_default();
+	let data_dirs = if data_dirs.is_empty() { "/usr/local/share:/usr/share" } else { &data_dirs };
+
+	dirs::data_dir().into_iter()
+		.chain(std::env::split_paths(data_dirs))
+		.map(|dir| dir.join("applications"))
+		.collect()
+}
+
+fn read_desktop_icon(path: &Path) -> Option<String> {
+	let entry = std::fs::read_to_string(path).ok()?;
+	let mut in_desktop_entry = false;
 
-	if result.is_err() {
-		eprintln!("{}", format!("Window provider {} failed.", provider.to_string().bold()).bright_red());
+	for line in entry.lines() {
+		if line.starts_with('[') {
+			in_desktop_entry = line == "[Desktop Entry]";
+		} else if let Some(icon) = line.strip_prefix("Icon=").filter(|_| in_desktop_entry) {
+			return Some(icon.trim().to_string()).filter(|icon| !icon.is_empty());
+		}
 	}
 
-	result
+	None
 }
 
 pub async fn wait_for_exit() {
@@ -124,6 +124,7 @@ export default class WctxExtension extends Extension {
 
 		this.connectSignal(global.display, 'window-entered-monitor', (display, monitor, meta) => {
 			this.updateWindow(meta, 'display', monitor.toString())
+			this.updateWindow(meta, 'display_index', monitor.toString())
 		})
 	}
 
@@ -162,6 +163,10 @@ export default class WctxExtension extends Extension {
 			this.updateWindow(meta, 'type', this.getWindowType(meta))
 		})
 
+		this.connectSignal(meta, 'notify::decorated', () => {
+			this.updateWindow(meta, 'decorated', meta.decorated.toString())
+		})
+
 		this.connectSignal(meta, 'notify::fullscreen', () => {
 			this.updateWindow(meta, 'state', this.getWindowState(meta))
 		})
@@ -217,12 +222,15 @@ export default class WctxExtension extends Extension {
 			id: GLib.Variant.new_string(window.id || ''),
 			name: GLib.Variant.new_string(window.name || ''),
 			class: GLib.Variant.new_string(window.class || ''),
+			icon: GLib.Variant.new_string(window.icon || ''),
 			pid: GLib.Variant.new_int32(window.pid || 0),
 			title: GLib.Variant.new_string(window.title || ''),
 			type: GLib.Variant.new_string(window.type || ''),
 			role: GLib.Variant.new_string(window.role || ''),
 			state: GLib.Variant.new_string(window.state || ''),
+			decorated: GLib.Variant.new_boolean(window.decorated !== 'false'),
 			display: GLib.Variant.new_string(window.display || ''),
+			display_index: GLib.Variant.new_int32(Number(window.display_index) || 0),
 		};
 
 		this.dbus.call(
@@ -290,12 +298,15 @@ export default class WctxExtension extends Extension {
 			id: meta.get_id().toString(),
 			name: meta.get_wm_class(),
 			class: meta.get_wm_class_instance(),
+			icon: meta.get_gtk_application_id() || meta.get_sandboxed_app_id() || '',
 			pid: meta.get_pid(),
 			title: meta.title,
 			type: this.getWindowType(meta),
 			role: meta.get_role() || '',
 			state: this.getWindowState(meta),
+			decorated: meta.decorated.toString(),
 			display: meta.get_monitor().toString(),
+			display_index: meta.get_monitor(),
 		}
 	}
 
@@ -27,6 +27,12 @@ const windows = {
 	pointer: null,
 }
 
+// last values sent for each context, to avoid sending unchanged values
+const sent = {
+	active: {},
+	pointer: {},
+}
+
 checkActiveWindow()
 checkPointerWindow()
 
@@ -54,7 +60,20 @@ function addWindowListeners(window) {
 	window.captionChanged.connect(() => updateWindow(window, 'title'))
 	window.windowRoleChanged.connect(() => updateWindow(window, 'role'))
 	window.fullScreenChanged.connect(() => updateWindow(window, 'state'))
-	window.outputChanged.connect(() => updateWindow(window, 'display'))
+	window.noBorderChanged && window.noBorderChanged.connect(() => updateWindow(window, 'decorated'))
+	window.outputChanged.connect(() => {
+		updateWindow(window, 'display')
+		updateWindow(window, 'display_index')
+	})
+
+	window.desktopsChanged.connect(() => updateWindow(window, 'workspace'))
+	window.frameGeometryChanged.connect(() => {
+		updateWindow(window, 'x')
+		updateWindow(window, 'y')
+		updateWindow(window, 'width')
+		updateWindow(window, 'height')
+	})
+	window.tileChanged && window.tileChanged.connect(() => updateWindow(window, 'state'))
 
 	// KDE 6.3.1+
 	window.maximizedChanged && window.maximizedChanged.connect(() => updateWindow(window, 'state'))
@@ -68,12 +87,20 @@ function getWindowData(window, key) {
 			id: window.internalId.toString().slice(1, 9), // first part of uuid
 			name: window.resourceName,
 			class: window.resourceClass,
+			icon: window.desktopFileName || '',
 			pid: window.pid,
 			title: window.caption,
 			type: getWindowType(window),
 			role: window.windowRole,
 			state: getWindowState(window),
+			decorated: !window.noBorder,
 			display: window.output.name,
+			display_index: getDisplayIndex(window),
+			x: Math.round(window.frameGeometry.x),
+			y: Math.round(window.frameGeometry.y),
+			width: Math.round(window.frameGeometry.width),
+			height: Math.round(window.frameGeometry.height),
+			workspace: getWorkspace(window),
 		}
 	} else {
 		// changeable properties
@@ -82,7 +109,14 @@ function getWindowData(window, key) {
 			case 'title': return window.caption
 			case 'role': return window.windowRole
 			case 'state': return getWindowState(window)
+			case 'decorated': return (!window.noBorder).toString()
 			case 'display': return window.output.name
+			case 'display_index': return getDisplayIndex(window).toString()
+			case 'x': return Math.round(window.frameGeometry.x).toString()
+			case 'y': return Math.round(window.frameGeometry.y).toString()
+			case 'width': return Math.round(window.frameGeometry.width).toString()
+			case 'height': return Math.round(window.frameGeometry.height).toString()
+			case 'workspace': return getWorkspace(window)
 		}
 	}
 }
@@ -95,6 +129,7 @@ function sendWindow(ctx, window) {
 	windows[ctx] = window
 
 	const dict = getWindowData(window)
+	sent[ctx] = dict
 
 	callDBus('org.wctx', '/', 'org.wctx.Windows', 'SetWindow', ctx, dict)
 }
@@ -115,6 +150,11 @@ function updateWindow(window, key) {
 	const value = getWindowData(window, key)
 	if (value == null) return
 
+	// e.g. moving a window only changes its position, not its size
+	const contexts = context === 'both' ? ['active', 'pointer'] : [context]
+	if (contexts.every(ctx => String(sent[ctx][key]) === value)) return
+	contexts.forEach(ctx => sent[ctx][key] = value)
+
 	callDBus('org.wctx', '/', 'org.wctx.Windows', 'UpdateWindow', context, key, value)
 }
 
@@ -122,6 +162,15 @@ function getWindowType(window) {
 	return WINDOW_TYPES[window.windowType] || WINDOW_TYPES[0]
 }
 
+function getDisplayIndex(window) {
+	return Math.max(workspace.screens.indexOf(window.output), 0)
+}
+
+function getWorkspace(window) {
+	// windows on all desktops have an empty list
+	return window.desktops.map(desktop => desktop.name).join(',')
+}
+
 function getWindowState(window) {
 	if (window.fullScreen) {
 		return 'FULLSCREEN'
@@ -131,6 +180,8 @@ function getWindowState(window) {
 		return 'HORIZONTAL'*/
 	} else if (window.maximizeMode === 3) {
 		return 'MAXIMIZED'
+	} else if (window.tile) {
+		return 'TILED'
 	} else {
 		return 'NORMAL'
 	}
@@ -12,10 +12,23 @@ const EXT_FILES: &[&[u8]] = &[
 ];
 
 pub fn detect() -> Option<WindowProvider> {
-	if env::var("XDG_SESSION_DESKTOP").unwrap_or_default() == "gnome" {
-		Some(WindowProvider::GNOME)
+	// e.g. "gnome", "gnome-xorg", "gnome-classic" or "gnome-wayland"
+	let session_desktop = env::var("XDG_SESSION_DESKTOP").unwrap_or_default().to_lowercase();
+	// a colon-separated list, e.g. "GNOME", "ubuntu:GNOME" or "GNOME-Classic:GNOME"
+	let current_desktop = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+
+	let is_gnome = session_desktop.starts_with("gnome")
+		|| current_desktop.split(':').any(|desktop| desktop.eq_ignore_ascii_case("gnome") || desktop.eq_ignore_ascii_case("gnome-classic"));
+
+	if !is_gnome {
+		return None;
+	}
+
+	// GNOME on Xorg works with the X11 provider, without needing the extension
+	if session_desktop == "gnome-xorg" || env::var("XDG_SESSION_TYPE").unwrap_or_default() == "x11" {
+		Some(WindowProvider::X11)
 	} else {
-		None
+		Some(WindowProvider::GNOME)
 	}
 }
 
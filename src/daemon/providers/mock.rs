@@ -0,0 +1,118 @@
+use super::*;
+use super::super::record::{Call, Record};
+use std::fs::File;
+use std::io::{self, Read};
+use serde_json::Value as JsonValue;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep_until, Instant};
+
+pub async fn serve(service: &ServiceProxy<'_>, args: &Args) -> Result<()> {
+	let sink = WindowSink::Service(service);
+
+	// read from stdin by default, setting windows as soon as they're received
+	let (reader, interval): (Box<dyn Read + Send>, _) = match &args.source {
+		Some(path) if path.as_os_str() != "-" => (Box::new(File::open(path)?), Some(Duration::from_millis(args.interval))),
+		_ => (Box::new(io::stdin()), None),
+	};
+
+	let (mut rx, reader_task) = read_values(reader);
+
+	let mut first = true;
+
+	while let Some(value) = rx.recv().await {
+		for (context, window) in get_snapshots(value)? {
+			if let Some(interval) = interval {
+				if !first {
+					sleep(interval).await;
+				}
+			}
+
+			first = false;
+			sink.set_window(context, window).await?;
+		}
+	}
+
+	reader_task.await??;
+
+	wait_for_exit().await;
+
+	Ok(())
+}
+
+/// Feed back window changes recorded with `--record`, keeping their original timing
+pub async fn replay(service: &ServiceProxy<'_>, args: &Args) -> Result<()> {
+	let reader: Box<dyn Read + Send> = match &args.source {
+		Some(path) if path.as_os_str() != "-" => Box::new(File::open(path)?),
+		_ => Box::new(io::stdin()),
+	};
+
+	let (mut rx, reader_task) = read_values(reader);
+	let start = Instant::now();
+
+	while let Some(value) = rx.recv().await {
+		let record: Record = serde_json::from_value(value)
+			.map_err(|err| anyhow!("Invalid record: {}", err))?;
+
+		sleep_until(start + Duration::from_millis(record.time)).await;
+
+		match record.call {
+			Call::SetWindow { context, window } => service.windows.set_window(context, window.into()).await?,
+			Call::UpdateWindow { context, key, value } => service.windows.update_window(context, key, &value).await?,
+			Call::UpdateWindowProps { context, props } => service.windows.update_window_props(context, props).await?,
+		}
+	}
+
+	reader_task.await??;
+
+	wait_for_exit().await;
+
+	Ok(())
+}
+
+/// Parse concatenated JSON values (e.g. the output of `wctx active -f json --watch`) on a blocking thread
+fn read_values(reader: Box<dyn Read + Send>) -> (mpsc::UnboundedReceiver<JsonValue>, JoinHandle<Result<()>>) {
+	let (tx, rx) = mpsc::unbounded_channel();
+
+	let reader_task = tokio::task::spawn_blocking(move || -> Result<()> {
+		for value in serde_json::Deserializer::from_reader(reader).into_iter::<JsonValue>() {
+			if tx.send(value?).is_err() {
+				break;
+			}
+		}
+
+		Ok(())
+	});
+
+	(rx, reader_task)
+}
+
+/// Get the windows from a snapshot, which can be a single window (set as both active and pointer),
+/// an object with `active` and/or `pointer` windows, or an array of either
+fn get_snapshots(value: JsonValue) -> Result<Vec<(WindowContext, WindowDict)>> {
+	match value {
+		JsonValue::Array(values) => {
+			let mut snapshots = Vec::new();
+
+			for value in values {
+				snapshots.extend(get_snapshots(value)?);
+			}
+
+			Ok(snapshots)
+		}
+		JsonValue::Object(mut map) if map.contains_key("active") || map.contains_key("pointer") => {
+			let mut snapshots = Vec::new();
+
+			if let Some(active) = map.remove("active") {
+				snapshots.push((WindowContext::Active, WindowDict::from_json(active)?));
+			}
+
+			if let Some(pointer) = map.remove("pointer") {
+				snapshots.push((WindowContext::Pointer, WindowDict::from_json(pointer)?));
+			}
+
+			Ok(snapshots)
+		}
+		value => Ok(vec![(WindowContext::Both, WindowDict::from_json(value)?)]),
+	}
+}
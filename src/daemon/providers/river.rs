@@ -0,0 +1,241 @@
+use super::*;
+use std::collections::HashMap;
+use std::env;
+use tokio::sync::mpsc;
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle, event_created_child};
+use wayland_client::backend::ObjectId;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_output, wl_registry, wl_seat};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{zwlr_foreign_toplevel_handle_v1, zwlr_foreign_toplevel_manager_v1};
+use zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1 as ToplevelHandle;
+use zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1 as ToplevelManager;
+use protocol::zriver_status_manager_v1::ZriverStatusManagerV1;
+use protocol::zriver_seat_status_v1::{self, ZriverSeatStatusV1};
+
+#[allow(dead_code, non_upper_case_globals, unused_imports)]
+mod protocol {
+	use wayland_client;
+	use wayland_client::protocol::*;
+
+	pub mod __interfaces {
+		use wayland_client::protocol::__interfaces::*;
+		wayland_scanner::generate_interfaces!("src/daemon/providers/assets/river/river-status-unstable-v1.xml");
+	}
+
+	use self::__interfaces::*;
+	wayland_scanner::generate_client_code!("src/daemon/providers/assets/river/river-status-unstable-v1.xml");
+}
+
+pub fn detect() -> Option<WindowProvider> {
+	let is_river = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default().eq_ignore_ascii_case("river");
+
+	if is_river || (env::var("WAYLAND_DISPLAY").unwrap_or_default() != "" && has_river_status()) {
+		Some(WindowProvider::River)
+	} else {
+		None
+	}
+}
+
+/// Check whether the compositor advertises the river-status protocol
+fn has_river_status() -> bool {
+	let Ok(conn) = Connection::connect_to_env() else {
+		return false;
+	};
+
+	let Ok((globals, _)) = registry_queue_init::<State>(&conn) else {
+		return false;
+	};
+
+	globals.contents().with_list(|list| {
+		list.iter().any(|global| global.interface == ZriverStatusManagerV1::interface().name)
+	})
+}
+
+pub async fn serve(service: &ServiceProxy<'_>) -> Result<()> {
+	let sink = WindowSink::Service(service);
+	let (tx, mut rx) = mpsc::unbounded_channel();
+
+	// wayland-client dispatches events by blocking, so run the event loop on its own thread
+	let wayland_task = tokio::task::spawn_blocking(move || dispatch(tx));
+
+	while let Some(window) = rx.recv().await {
+		sink.set_window(WindowContext::Active, window).await?;
+	}
+
+	// the event loop only stops sending if it failed
+	wayland_task.await?
+}
+
+fn dispatch(tx: mpsc::UnboundedSender<WindowDict>) -> Result<()> {
+	let conn = Connection::connect_to_env()?;
+	let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+	let qh = queue.handle();
+
+	let status_manager: ZriverStatusManagerV1 = globals.bind(&qh, 1..=4, ())?;
+	let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=1, ())?;
+	status_manager.get_river_seat_status(&seat, &qh, ());
+
+	// river-status only reports the focused view's title, so app ids come from the toplevel list
+	let _toplevel_manager: Option<ToplevelManager> = globals.bind(&qh, 1..=3, ()).ok();
+
+	// bind outputs to learn their names (from version 4), using their position as the display index
+	let outputs = globals.contents().with_list(|list| {
+		list.iter()
+			.filter(|global| global.interface == wl_output::WlOutput::interface().name)
+			.map(|global| (global.name, global.version.min(4)))
+			.collect::<Vec<_>>()
+	});
+
+	for (index, (name, version)) in outputs.into_iter().enumerate() {
+		globals.registry().bind::<wl_output::WlOutput, _, _>(name, version, &qh, index as u32);
+	}
+
+	let mut state = State {
+		tx: Some(tx),
+		..Default::default()
+	};
+
+	loop {
+		queue.blocking_dispatch(&mut state)?;
+
+		if state.tx.as_ref().map_or(true, |tx| tx.is_closed()) {
+			return Ok(());
+		}
+	}
+}
+
+#[derive(Default)]
+struct State {
+	tx: Option<mpsc::UnboundedSender<WindowDict>>,
+	outputs: HashMap<ObjectId, String>,
+	toplevels: HashMap<ObjectId, Toplevel>,
+	focused_output: Option<wl_output::WlOutput>,
+	focused_title: String,
+}
+
+#[derive(Default)]
+struct Toplevel {
+	app_id: String,
+	activated: bool,
+	state: WindowState,
+}
+
+impl State {
+	fn send_window(&self) {
+		let Some(tx) = &self.tx else {
+			return;
+		};
+
+		let (display, display_index) = match &self.focused_output {
+			Some(output) => (
+				self.outputs.get(&output.id()).cloned().unwrap_or_default(),
+				output.data::<u32>().copied().unwrap_or_default(),
+			),
+			None => Default::default(),
+		};
+
+		let window = match self.toplevels.iter().find(|(_, toplevel)| toplevel.activated) {
+			Some((id, toplevel)) => WindowDict {
+				id: id.protocol_id().to_string(),
+				name: toplevel.app_id.clone(),
+				class: toplevel.app_id.clone(),
+				title: self.focused_title.clone(),
+				r#type: WindowType::Normal,
+				state: toplevel.state,
+				display,
+				display_index,
+				..Default::default()
+			},
+			None => WindowDict {
+				title: self.focused_title.clone(),
+				display,
+				display_index,
+				..Default::default()
+			},
+		};
+
+		let _ = tx.send(window);
+	}
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+	fn event(_: &mut Self, _: &wl_registry::WlRegistry, _: wl_registry::Event, _: &GlobalListContents, _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for State {
+	fn event(_: &mut Self, _: &wl_seat::WlSeat, _: wl_seat::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<ZriverStatusManagerV1, ()> for State {
+	fn event(_: &mut Self, _: &ZriverStatusManagerV1, _: <ZriverStatusManagerV1 as Proxy>::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<wl_output::WlOutput, u32> for State {
+	fn event(state: &mut Self, output: &wl_output::WlOutput, event: wl_output::Event, _: &u32, _: &Connection, _: &QueueHandle<Self>) {
+		if let wl_output::Event::Name { name } = event {
+			state.outputs.insert(output.id(), name);
+			state.send_window();
+		}
+	}
+}
+
+impl Dispatch<ZriverSeatStatusV1, ()> for State {
+	fn event(state: &mut Self, _: &ZriverSeatStatusV1, event: zriver_seat_status_v1::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+		match event {
+			zriver_seat_status_v1::Event::FocusedOutput { output } => state.focused_output = Some(output),
+			zriver_seat_status_v1::Event::FocusedView { title } => state.focused_title = title,
+			_ => return,
+		}
+
+		state.send_window();
+	}
+}
+
+impl Dispatch<ToplevelManager, ()> for State {
+	fn event(state: &mut Self, _: &ToplevelManager, event: zwlr_foreign_toplevel_manager_v1::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+		if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+			state.toplevels.insert(toplevel.id(), Toplevel::default());
+		}
+	}
+
+	event_created_child!(State, ToplevelManager, [
+		zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ToplevelHandle, ()),
+	]);
+}
+
+impl Dispatch<ToplevelHandle, ()> for State {
+	fn event(state: &mut Self, handle: &ToplevelHandle, event: zwlr_foreign_toplevel_handle_v1::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+		let Some(toplevel) = state.toplevels.get_mut(&handle.id()) else {
+			return;
+		};
+
+		match event {
+			zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => toplevel.app_id = app_id,
+			zwlr_foreign_toplevel_handle_v1::Event::State { state: flags } => {
+				let flags: Vec<u32> = flags.chunks_exact(4)
+					.map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+					.collect();
+
+				let has = |flag: zwlr_foreign_toplevel_handle_v1::State| flags.contains(&u32::from(flag));
+
+				toplevel.activated = has(zwlr_foreign_toplevel_handle_v1::State::Activated);
+				// wlr-foreign-toplevel has no tiled or floating state, so river windows are never reported as either
+				toplevel.state = if has(zwlr_foreign_toplevel_handle_v1::State::Fullscreen) {
+					WindowState::Fullscreen
+				} else if has(zwlr_foreign_toplevel_handle_v1::State::Maximized) {
+					WindowState::Maximized
+				} else {
+					WindowState::Normal
+				};
+			}
+			// changes to a toplevel are applied atomically on `done`
+			zwlr_foreign_toplevel_handle_v1::Event::Done => state.send_window(),
+			zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+				state.toplevels.remove(&handle.id());
+				handle.destroy();
+				state.send_window();
+			}
+			_ => {}
+		}
+	}
+}
@@ -0,0 +1,160 @@
+use super::*;
+use std::env;
+use serde_json::{json, Value as JsonValue};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+/// View events that change the properties of a window
+const WATCH_EVENTS: [&str; 7] = [
+	"view-focused",
+	"view-mapped",
+	"view-title-changed",
+	"view-app-id-changed",
+	"view-set-output",
+	"view-fullscreen",
+	"view-tiled",
+];
+
+pub fn detect() -> Option<WindowProvider> {
+	if env::var("WAYFIRE_SOCKET").unwrap_or_default() != "" {
+		Some(WindowProvider::Wayfire)
+	} else {
+		None
+	}
+}
+
+pub async fn serve(service: &ServiceProxy<'_>) -> Result<()> {
+	let sink = WindowSink::Service(service);
+	let socket_path = env::var("WAYFIRE_SOCKET")?;
+
+	// events are streamed on their own connection so they don't interleave with request replies
+	let mut requests = Ipc::connect(&socket_path).await?;
+	let mut events = Ipc::connect(&socket_path).await?;
+
+	events.request("window-rules/events/watch", json!({ "events": WATCH_EVENTS })).await?;
+
+	let mut outputs = list_outputs(&mut requests).await?;
+
+	// the simple-tile plugin tiles views by default, so untiled views are floating rather than normal
+	let tiling = has_method(&mut requests, "simple-tile/get-layout").await.unwrap_or_default();
+
+	let focused = requests.request("window-rules/get-focused-view", json!({})).await?;
+	let mut active_id = focused["info"]["id"].as_u64();
+
+	sink.set_window(WindowContext::Active, get_window(&focused["info"], &outputs, tiling)).await?;
+
+	loop {
+		let message = events.read().await?;
+		let view = &message["view"];
+
+		match message["event"].as_str().unwrap_or_default() {
+			"view-focused" => {
+				active_id = view["id"].as_u64();
+			}
+			// newly mapped views can be activated without a separate focus event
+			"view-mapped" if view["activated"].as_bool() == Some(true) => {
+				active_id = view["id"].as_u64();
+			}
+			"view-set-output" if active_id.is_some() && view["id"].as_u64() == active_id => {
+				outputs = list_outputs(&mut requests).await?;
+			}
+			_ if active_id.is_some() && view["id"].as_u64() == active_id => {}
+			_ => continue,
+		}
+
+		sink.set_window(WindowContext::Active, get_window(view, &outputs, tiling)).await?;
+	}
+}
+
+async fn list_outputs(ipc: &mut Ipc) -> Result<Vec<String>> {
+	let outputs = ipc.request("window-rules/list-outputs", json!({})).await?;
+
+	Ok(outputs.as_array().into_iter().flatten()
+		.filter_map(|output| output["name"].as_str().map(Into::into))
+		.collect())
+}
+
+/// Whether the IPC has a method, such as one registered by an optional plugin
+async fn has_method(ipc: &mut Ipc, method: &str) -> Result<bool> {
+	let methods = ipc.request("list-methods", json!({})).await?;
+
+	Ok(methods["methods"].as_array().into_iter().flatten().any(|name| name.as_str() == Some(method)))
+}
+
+fn get_window(view: &JsonValue, outputs: &[String], tiling: bool) -> WindowDict {
+	let Some(id) = view["id"].as_u64() else {
+		return WindowDict::default();
+	};
+
+	let app_id = view["app-id"].as_str().unwrap_or_default();
+	let display = view["output-name"].as_str().unwrap_or_default();
+
+	let r#type = match view["role"].as_str().unwrap_or_default() {
+		"desktop-environment" => WindowType::Desktop,
+		"unmanaged" => WindowType::Override,
+		_ => WindowType::Normal,
+	};
+
+	// tiled edges are a bitmask of top, bottom, left and right
+	let state = match (view["fullscreen"].as_bool(), view["tiled-edges"].as_u64()) {
+		(Some(true), _) => WindowState::Fullscreen,
+		// views fill their tile on all edges on a tiling layout, so they aren't maximized
+		(_, Some(1..)) if tiling => WindowState::Tiled,
+		(_, Some(15)) => WindowState::Maximized,
+		(_, Some(1..)) => WindowState::Tiled,
+		_ if tiling => WindowState::Floating,
+		_ => WindowState::Normal,
+	};
+
+	WindowDict {
+		id: id.to_string(),
+		name: app_id.into(),
+		class: app_id.into(),
+		pid: view["pid"].as_u64().and_then(|pid| u32::try_from(pid).ok()).unwrap_or_default(),
+		title: view["title"].as_str().unwrap_or_default().into(),
+		r#type,
+		state,
+		display: display.into(),
+		display_index: outputs.iter().position(|name| name == display).unwrap_or_default() as u32,
+		..Default::default()
+	}
+}
+
+/// Connection to the Wayfire IPC socket, which sends JSON messages prefixed by their length
+struct Ipc {
+	stream: UnixStream,
+}
+
+impl Ipc {
+	async fn connect(path: &str) -> Result<Self> {
+		let stream = UnixStream::connect(path).await
+			.map_err(|err| anyhow!("Couldn't connect to the Wayfire IPC socket (are the ipc and ipc-rules plugins enabled?): {}", err))?;
+
+		Ok(Self { stream })
+	}
+
+	async fn request(&mut self, method: &str, data: JsonValue) -> Result<JsonValue> {
+		let message = serde_json::to_vec(&json!({ "method": method, "data": data }))?;
+
+		self.stream.write_all(&(message.len() as u32).to_le_bytes()).await?;
+		self.stream.write_all(&message).await?;
+
+		let response = self.read().await?;
+
+		if let Some(error) = response.get("error") {
+			return Err(anyhow!("Wayfire IPC request {} failed: {}", method, error));
+		}
+
+		Ok(response)
+	}
+
+	async fn read(&mut self) -> Result<JsonValue> {
+		let mut len = [0; 4];
+		self.stream.read_exact(&mut len).await?;
+
+		let mut message = vec![0; u32::from_le_bytes(len) as usize];
+		self.stream.read_exact(&mut message).await?;
+
+		Ok(serde_json::from_slice(&message)?)
+	}
+}
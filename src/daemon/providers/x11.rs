@@ -3,11 +3,47 @@ use crate::daemon::debouncer::Debouncer;
 use std::env;
 use std::collections::{HashMap, HashSet};
 use anyhow::Result;
-use tokio::time::Duration;
-use x11rb_async::connection::Connection;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use x11rb_async::connection::{Connection, RequestConnection};
 use x11rb_async::rust_connection::RustConnection;
 use x11rb_async::protocol::{Event, xproto::*, randr::*};
 use x11rb_async::protocol::randr::ConnectionExt as _;
+use x11rb_async::protocol::res::{self, ConnectionExt as _};
+use x11rb_async::protocol::xinput::{self, ConnectionExt as _};
+use zbus::{fdo, interface};
+
+/// Initial length to read for window properties (in 32-bit units)
+const PROP_READ_LEN: u32 = 1024;
+
+/// Upper bound for reading long window properties (in 32-bit units)
+const PROP_MAX_LEN: u32 = 64 * 1024;
+
+/// Flag in `_MOTIF_WM_HINTS` for whether the decorations field is set
+const MWM_HINTS_DECORATIONS: u32 = 1 << 1;
+
+/// Value of `_NET_WM_DESKTOP` for windows on all desktops (sticky windows)
+const ALL_DESKTOPS: u32 = 0xFFFFFFFF;
+
+/// Flag in `WM_HINTS` for whether the input field is set
+const WM_HINTS_INPUT: u32 = 1 << 0;
+
+/// The `_NET_WM_STATE_*` atoms listed in the `states` property, without the prefix
+const WINDOW_STATES: [&str; 13] = [
+	"MODAL",
+	"STICKY",
+	"MAXIMIZED_VERT",
+	"MAXIMIZED_HORZ",
+	"SHADED",
+	"SKIP_TASKBAR",
+	"SKIP_PAGER",
+	"HIDDEN",
+	"FULLSCREEN",
+	"ABOVE",
+	"BELOW",
+	"DEMANDS_ATTENTION",
+	"FOCUSED",
+];
 
 pub fn detect() -> Option<WindowProvider> {
 	if env::var("XDG_SESSION_TYPE").unwrap_or_default() == "x11" {
@@ -17,44 +53,78 @@ pub fn detect() -> Option<WindowProvider> {
 	}
 }
 
-pub async fn serve(service: &ServiceProxy<'_>) -> Result<()> {
-	let mut x = X11::new(service).await?;
+pub async fn serve(sink: WindowSink<'_>, args: &Args) -> Result<()> {
+	let mut x = X11::new(sink, args.display_mode, args.active_only, args.track_override, !args.no_visible_name, args.connector_names, args.seat.as_deref()).await?;
 
-	// register window events
-	let event_mask = ChangeWindowAttributesAux::new().event_mask(EventMask::SUBSTRUCTURE_NOTIFY | EventMask::FOCUS_CHANGE | EventMask::ENTER_WINDOW | EventMask::PROPERTY_CHANGE);
-	x.conn.change_window_attributes(x.root, &event_mask).await?;
+	// clients restack windows and query the input focus through the daemon's D-Bus service, which isn't available in standalone mode
+	let (restack_tx, mut restack_rx) = mpsc::unbounded_channel();
+	let (focus_tx, mut focus_rx) = mpsc::unbounded_channel();
 
-	let event_mask = event_mask.event_mask(EventMask::FOCUS_CHANGE | EventMask::ENTER_WINDOW | EventMask::PROPERTY_CHANGE);
+	if let WindowSink::Service(service) = &x.sink {
+		let server = service.windows.inner().connection().object_server();
+		// a restarted provider replaces the interfaces served by the previous one
+		let _ = server.remove::<Stacking, _>("/").await;
+		let _ = server.remove::<Focus, _>("/").await;
+		server.at("/", Stacking { tx: restack_tx }).await?;
+		server.at("/", Focus { tx: focus_tx }).await?;
+	}
+
+	// register window events (the pointer window is tracked with enter events)
+	let mut root_events = EventMask::SUBSTRUCTURE_NOTIFY | EventMask::FOCUS_CHANGE | EventMask::PROPERTY_CHANGE;
 
-	for win_id in x.conn.query_tree(x.root).await?.reply().await?.children {
-		x.cascade_event_mask(win_id, &event_mask).await?;
+	if !x.active_only {
+		root_events |= EventMask::ENTER_WINDOW;
 	}
 
-	// register randr events
-	x.conn.randr_select_input(x.root, NotifyMask::SCREEN_CHANGE | NotifyMask::OUTPUT_CHANGE | NotifyMask::CRTC_CHANGE).await?;
+	let root_event_mask = ChangeWindowAttributesAux::new().event_mask(root_events);
+	let event_mask = window_event_mask(x.active_only);
+
+	// watch every screen, for multi-screen ("Zaphod") setups with a separate root window per screen
+	for root in x.roots.clone() {
+		x.conn.change_window_attributes(root, &root_event_mask).await?;
+
+		for win_id in x.conn.query_tree(root).await?.reply().await?.children {
+			x.cascade_event_mask(win_id, &event_mask).await?;
+		}
+
+		// register randr events
+		x.conn.randr_select_input(root, NotifyMask::SCREEN_CHANGE | NotifyMask::OUTPUT_CHANGE | NotifyMask::CRTC_CHANGE).await?;
+	}
 
 	// flush to send to X11 server
 	x.conn.flush().await?;
 
+	x.send_displays().await?;
+
 	// determine initial windows
-	if let Some(active_window) = x.query_active_window().await {
-		x.set_window(WindowContext::Active, active_window).await?;
-	}
+	let active_window = x.query_active_window().await.unwrap_or_default();
+	x.set_window(WindowContext::Active, active_window).await?;
 
-	if let Some(pointer_window) = x.query_pointer_window().await {
+	if !x.active_only {
+		let pointer_window = x.query_pointer_window().await.unwrap_or_default();
 		x.set_window(WindowContext::Pointer, pointer_window).await?;
 	}
 
-	// debouncers for window move events
-	let mut active_move_debouncer = Debouncer::new(Duration::from_millis(15));
-	let mut pointer_move_debouncer = Debouncer::new(Duration::from_millis(15));
+	// windows with ignored classes never become the active or pointer window
+	let is_ignored_class = |class: &str| args.ignore_class.iter().any(|re| re.is_match(class));
+
+	// debouncers for window move events (leading edge so the geometry is reported as soon as a move starts)
+	let move_debounce = Duration::from_millis(args.move_debounce);
+	let mut active_move_debouncer = Debouncer::new_with(move_debounce, true);
+	let mut pointer_move_debouncer = Debouncer::new_with(move_debounce, true);
+
+	// safety net for window managers that don't deliver enter events for every window (disabled at 0)
+	let poll_pointer = args.poll_pointer > 0 && !x.active_only;
+	let mut pointer_poll = interval(Duration::from_millis(args.poll_pointer.max(1)));
+	pointer_poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
 	loop {
 		tokio::select! {
 			event = x.conn.wait_for_event() => {
 				match event? {
 					Event::CreateNotify(e) => {
-						if e.override_redirect {
+						// override-redirect windows (e.g. menus and tooltips) aren't managed by the window manager
+						if e.override_redirect && !x.track_override {
 							continue;
 						}
 
@@ -63,6 +133,20 @@ pub async fn serve(service: &ServiceProxy<'_>) -> Result<()> {
 						}
 					},
 					Event::FocusIn(e) => {
+						// focus events are sent for the keyboards of every seat, so resolve the focus of the seat's own keyboard
+						if x.seat.is_some() {
+							let Some(window) = x.query_focus_window().await else {
+								continue;
+							};
+
+							if window.id == x.active_window.id || is_ignored_class(&window.class) || args.ignore_types.contains(&window.r#type) {
+								continue;
+							}
+
+							x.set_window(WindowContext::Active, window).await?;
+							continue;
+						}
+
 						if e.mode != NotifyMode::NORMAL || e.detail != NotifyDetail::NONLINEAR_VIRTUAL {
 							continue;
 						}
@@ -78,32 +162,54 @@ pub async fn serve(service: &ServiceProxy<'_>) -> Result<()> {
 								continue;
 							};
 
-							if win_match.0 == x.active_window.id {
+							if win_match.0 == x.active_window.id || is_ignored_class(&win_match.2) {
 								continue;
 							}
 
 							x.get_window(e.event, win_match).await
 						};
 
+						// keep the last application window active when transient windows (e.g. menus, docks) gain focus
+						if args.ignore_types.contains(&window.r#type) {
+							continue;
+						}
+
 						x.set_window(WindowContext::Active, window).await?;
 					},
 					Event::EnterNotify(e) => {
-						if e.event == x.pointer_window.id || e.event == x.pointer_window.top_id || e.child == x.pointer_window.id {
+						// enter events are sent for the pointers of every seat, so resolve the window under the seat's own pointer
+						if x.seat.is_some() {
+							let window = x.query_pointer_window().await.unwrap_or_default();
+
+							if window.id == x.pointer_window.id || is_ignored_class(&window.class) {
+								continue;
+							}
+
+							x.set_window(WindowContext::Pointer, window).await?;
 							continue;
 						}
 
+						match pointer_enter(&x.roots, &x.pointer_window, e.event, e.child, e.mode) {
+							PointerEnter::Ignore => continue,
+							PointerEnter::Clear => {
+								x.set_window(WindowContext::Pointer, XWindow::default()).await?;
+								continue;
+							}
+							PointerEnter::Resolve => {}
+						}
+
 						let window = if e.event == x.active_window.id || e.event == x.active_window.top_id {
 							let mut window = x.active_window.clone();
 							// need to recalculate display when moving window under mouse (e.g. with keyboard)
 							// from another display, because active window display won't be updated just yet
-							window.display = x.get_window_display(window.id).await.unwrap_or_default();
+							window.update(x.display_prop(x.get_window_display(window.id).await.unwrap_or_default()));
 							window
 						} else {
 							let Some(win_match) = x.resolve_window_match(e.event).await else {
 								continue;
 							};
 
-							if win_match.0 == x.pointer_window.id {
+							if win_match.0 == x.pointer_window.id || is_ignored_class(&win_match.2) {
 								continue;
 							}
 
@@ -113,54 +219,173 @@ pub async fn serve(service: &ServiceProxy<'_>) -> Result<()> {
 						x.set_window(WindowContext::Pointer, window).await?;
 					},
 					Event::PropertyNotify(e) => {
+						if x.roots.contains(&e.window) {
+							if e.atom == x.atoms.CLIENT_LIST_STACKING {
+								// the stacking order is per screen, so only windows on this screen are affected
+								let stacking = x.get_stacking_order(e.window).await.unwrap_or_default();
+
+								if x.active_window.root == e.window {
+									let active_stack = x.active_window.stack_prop(&stacking);
+									x.update_window_prop(x.active_window.id, active_stack).await?;
+								}
+
+								if x.pointer_window.root == e.window {
+									let pointer_stack = x.pointer_window.stack_prop(&stacking);
+									x.update_window_prop(x.pointer_window.id, pointer_stack).await?;
+								}
+							} else if e.atom == x.atoms.DESKTOP_NAMES {
+								// renaming a desktop changes the workspace of the windows on it
+								for win_id in [x.active_window.id, x.pointer_window.id] {
+									let workspace = x.get_window_workspace(win_id).await.unwrap_or_default();
+									x.update_window_prop(win_id, XUpdateProp::Workspace(workspace)).await?;
+								}
+							} else if e.atom == x.atoms.ACTIVE_WINDOW && x.seat.is_none() {
+								// some window managers update the EWMH property without consistently delivering focus events,
+								// and when both arrive the second one resolves to the same window so isn't sent again
+								let Some(win_id) = x.get_active_window_id(e.window).await else {
+									continue;
+								};
+
+								if win_id == x.active_window.id || win_id == x.active_window.top_id {
+									continue;
+								}
+
+								let Some(win_match) = x.resolve_window_match(win_id).await else {
+									continue;
+								};
+
+								if win_match.0 == x.active_window.id || is_ignored_class(&win_match.2) {
+									continue;
+								}
+
+								let window = x.get_window(win_id, win_match).await;
+
+								if args.ignore_types.contains(&window.r#type) {
+									continue;
+								}
+
+								x.set_window(WindowContext::Active, window).await?;
+							}
+
+							continue;
+						}
+
+						if e.atom == x.atoms.WM_USER_TIME {
+							let win_id = if e.window == x.active_window.user_time_id {
+								x.active_window.id
+							} else if e.window == x.pointer_window.user_time_id {
+								x.pointer_window.id
+							} else {
+								continue;
+							};
+
+							let user_time = x.get_user_time(e.window).await.unwrap_or_default();
+							x.update_window_prop(win_id, XUpdateProp::UserTime(user_time)).await?;
+							continue;
+						}
+
 						if e.window != x.active_window.id && e.window != x.pointer_window.id {
 							continue;
 						}
 
-						if e.atom == x.atoms.WM_NAME {
-							let new_title = x.get_window_title(e.window).await.unwrap_or_default();
+						let is_title = e.atom == x.atoms.WM_NAME
+							|| e.atom == u32::from(AtomEnum::WM_NAME)
+							|| (x.visible_name && e.atom == x.atoms.WM_VISIBLE_NAME);
 
-							if e.window == x.active_window.id && new_title != x.active_window.title {
-								x.update_window(WindowContext::Active, XUpdateProp::Title(new_title)).await?;
-							} else if e.window == x.pointer_window.id && new_title != x.pointer_window.title {
-								x.update_window(WindowContext::Pointer, XUpdateProp::Title(new_title)).await?;
-							}
+						let prop = if is_title {
+							XUpdateProp::Title(x.get_window_title(e.window).await.unwrap_or_default())
 						} else if e.atom == x.atoms.WM_STATE {
-							let new_state = x.get_window_state(e.window).await.unwrap_or_default();
+							let (state, states) = x.get_window_state(e.window).await.unwrap_or_default();
+							XUpdateProp::State(state, states)
+						} else if e.atom == u32::from(AtomEnum::WM_CLASS) {
+							// some apps (e.g. Electron, Wine) change their class after mapping
+							let Some((_, name, class)) = x.get_window_match(e.window).await else {
+								continue;
+							};
 
-							if e.window == x.active_window.id && new_state != x.active_window.state {
-								x.update_window(WindowContext::Active, XUpdateProp::State(new_state)).await?;
-							} else if e.window == x.pointer_window.id && new_state != x.pointer_window.state {
-								x.update_window(WindowContext::Pointer, XUpdateProp::State(new_state)).await?;
-							}
-						}
+							XUpdateProp::Class(name, class)
+						} else if e.atom == x.atoms.WM_WINDOW_TYPE {
+							XUpdateProp::Type(x.get_window_type(e.window).await.unwrap_or_default())
+						} else if e.atom == x.atoms.MOTIF_WM_HINTS || e.atom == x.atoms.GTK_FRAME_EXTENTS {
+							XUpdateProp::Decorated(x.get_window_decorated(e.window).await)
+						} else if e.atom == x.atoms.WM_WINDOW_ROLE {
+							XUpdateProp::Role(x.get_window_role(e.window).await.unwrap_or_default())
+						} else if e.atom == x.atoms.WM_DESKTOP {
+							// moving a window to another desktop doesn't change the focus
+							XUpdateProp::Workspace(x.get_window_workspace(e.window).await.unwrap_or_default())
+						} else {
+							continue;
+						};
+
+						x.update_window_prop(e.window, prop).await?;
 					},
 					Event::RandrNotify(_) | Event::RandrScreenChangeNotify(_) => {
-						x.displays = get_displays(&x.conn, x.root).await?;
+						x.displays = get_displays(&x.conn, &x.roots, x.connector_names).await?;
+						x.display_index = DisplayIndex::new(&x.displays);
+						x.send_displays().await?;
 					}
 					Event::ConfigureNotify(e) => {
-						if e.override_redirect {
+						if e.override_redirect && !x.track_override {
 							continue;
 						}
 
-						if e.window == x.active_window.top_id {
-							active_move_debouncer.push(e);
+						let context = if e.window == x.active_window.top_id {
+							WindowContext::Active
 						} else if e.window == x.pointer_window.top_id {
+							WindowContext::Pointer
+						} else {
+							continue;
+						};
+
+						let new_geometry = XUpdateProp::Geometry(e.x.into(), e.y.into(), e.width.into(), e.height.into());
+						let new_display = x.display_prop(x.calc_window_display(e.event, e.x, e.y, e.width, e.height));
+						let window = if context == WindowContext::Active { &x.active_window } else { &x.pointer_window };
+
+						let (props, debounce) = move_update(window, new_geometry, new_display, !move_debounce.is_zero());
+
+						if let Some(props) = props {
+							x.update_window(context, props).await?;
+						}
+
+						if !debounce {
+							continue;
+						}
+
+						if context == WindowContext::Active {
+							active_move_debouncer.push(e);
+						} else {
 							pointer_move_debouncer.push(e);
 						}
 					}
 					_ => {}
 				}
 			}
+			_ = pointer_poll.tick(), if poll_pointer => {
+				let Some(window) = x.query_pointer_window().await else {
+					continue;
+				};
+
+				if window.id == x.pointer_window.id || is_ignored_class(&window.class) {
+					continue;
+				}
+
+				x.set_window(WindowContext::Pointer, window).await?;
+			}
+			Some((id, mode, reply)) = restack_rx.recv() => {
+				let _ = reply.send(x.restack_window(&id, mode).await.map_err(|err| err.to_string()));
+			}
+			Some(reply) = focus_rx.recv() => {
+				let _ = reply.send(x.query_focus_window().await.unwrap_or_default().as_dict());
+			}
 			Some(e) = active_move_debouncer.next() => {
 				if e.window != x.active_window.top_id {
 					continue;
 				}
 
-				let new_display = x.calc_window_display(e.x, e.y, e.width, e.height).unwrap_or_default();
+				let new_geometry = XUpdateProp::Geometry(e.x.into(), e.y.into(), e.width.into(), e.height.into());
 
-				if new_display != x.active_window.display {
-					x.update_window(WindowContext::Active, XUpdateProp::Display(new_display)).await?;
+				if let Some(props) = debounced_move_update(&x.active_window, new_geometry) {
+					x.update_window(WindowContext::Active, props).await?;
 				}
 			}
 			Some(e) = pointer_move_debouncer.next() => {
@@ -168,51 +393,304 @@ pub async fn serve(service: &ServiceProxy<'_>) -> Result<()> {
 					continue;
 				}
 
-				let new_display = x.calc_window_display(e.x, e.y, e.width, e.height).unwrap_or_default();
+				let new_geometry = XUpdateProp::Geometry(e.x.into(), e.y.into(), e.width.into(), e.height.into());
 
-				if new_display != x.pointer_window.display {
-					x.update_window(WindowContext::Pointer, XUpdateProp::Display(new_display)).await?;
+				if let Some(props) = debounced_move_update(&x.pointer_window, new_geometry) {
+					x.update_window(WindowContext::Pointer, props).await?;
 				}
 			}
 		}
 	}
 }
 
-async fn get_displays(conn: &RustConnection, root: Window) -> Result<Vec<XDisplay>> {
-	let reply = conn.randr_get_monitors(root, true).await?.reply().await?;
+/// A request to restack a window (or the active window if the id is empty), with a channel for the result
+type RestackRequest = (String, StackMode, oneshot::Sender<Result<(), String>>);
+
+struct Stacking {
+	tx: mpsc::UnboundedSender<RestackRequest>,
+}
+
+#[interface(name = "org.wctx.Stacking")]
+impl Stacking {
+	/// Raise a window above the other windows, or the active window if the id is empty
+	async fn raise_window(&self, id: &str) -> fdo::Result<()> {
+		self.restack(id, StackMode::ABOVE).await
+	}
+
+	/// Lower a window below the other windows, or the active window if the id is empty
+	async fn lower_window(&self, id: &str) -> fdo::Result<()> {
+		self.restack(id, StackMode::BELOW).await
+	}
+}
+
+impl Stacking {
+	async fn restack(&self, id: &str, mode: StackMode) -> fdo::Result<()> {
+		let (tx, rx) = oneshot::channel();
+		let stopped = || fdo::Error::Failed("The window provider stopped".into());
+
+		self.tx.send((id.to_string(), mode, tx)).map_err(|_| stopped())?;
+		rx.await.map_err(|_| stopped())?.map_err(fdo::Error::Failed)
+	}
+}
+
+/// A request for the window with the input focus
+type FocusRequest = oneshot::Sender<WindowDict>;
+
+struct Focus {
+	tx: mpsc::UnboundedSender<FocusRequest>,
+}
+
+#[interface(name = "org.wctx.InputFocus")]
+impl Focus {
+	/// Get the window with the X input focus, which can differ from the EWMH active window (e.g. during input grabs)
+	async fn focus_window(&self) -> fdo::Result<DictMap<'static>> {
+		let (tx, rx) = oneshot::channel();
+		let stopped = || fdo::Error::Failed("The window provider stopped".into());
+
+		self.tx.send(tx).map_err(|_| stopped())?;
+		let mut window = rx.await.map_err(|_| stopped())?;
+
+		// the service fills these in for the active and pointer windows
+		window.comm = process_comm(window.pid).unwrap_or_default();
+		window.icon = desktop_icon(&[&window.icon, &window.class, &window.name].map(String::as_str));
+
+		Ok(window.into())
+	}
+}
+
+fn window_event_mask(active_only: bool) -> ChangeWindowAttributesAux {
+	let mut events = EventMask::FOCUS_CHANGE | EventMask::PROPERTY_CHANGE;
+
+	if !active_only {
+		events |= EventMask::ENTER_WINDOW;
+	}
+
+	ChangeWindowAttributesAux::new().event_mask(events)
+}
+
+#[derive(Debug, PartialEq)]
+enum PointerEnter {
+	/// The pointer window is unchanged
+	Ignore,
+	/// The pointer is on no window
+	Clear,
+	/// The pointer window needs to be resolved from the entered window
+	Resolve,
+}
+
+/// Decide what an `EnterNotify` event on `event` (with the entered `child`) means for the current pointer window
+fn pointer_enter(roots: &[Window], pointer_window: &XWindow, event: Window, child: Window, mode: NotifyMode) -> PointerEnter {
+	// the child is 0 when entering a window directly, which doesn't mean it's the cleared pointer window
+	if event == pointer_window.id || event == pointer_window.top_id || (child != 0 && child == pointer_window.id) {
+		return PointerEnter::Ignore;
+	}
+
+	// the pointer moved onto the desktop itself rather than into a window, which resolves to no window,
+	// so clear the pointer window instead of leaving the last one (pointer grabs also cross the root)
+	if roots.contains(&event) && child == 0 {
+		return match mode {
+			NotifyMode::NORMAL => PointerEnter::Clear,
+			_ => PointerEnter::Ignore,
+		};
+	}
+
+	PointerEnter::Resolve
+}
+
+/// Get the client and user time windows of a window being set that need their event masks selected,
+/// leaving out those of the stored windows, which were selected when they were set
+fn windows_to_select(window: &XWindow, active_window: &XWindow, pointer_window: &XWindow) -> (Option<Window>, Option<Window>) {
+	let stored = [active_window, pointer_window];
+
+	// the client window may not have been reached when cascading the event mask (e.g. when it's reparented
+	// into a frame by the window manager), so make sure we get its property changes, such as title updates
+	let client = (window.id != 0 && window.id != window.top_id && stored.iter().all(|stored| stored.id != window.id))
+		.then_some(window.id);
+
+	// the user time window is usually an unmapped child that never gets the event mask otherwise
+	let user_time = (window.user_time_id != 0 && window.user_time_id != window.id && stored.iter().all(|stored| stored.user_time_id != window.user_time_id))
+		.then_some(window.user_time_id);
+
+	(client, user_time)
+}
+
+/// Get the props to update right away for a move or resize of a window, and whether to also debounce it
+fn move_update(window: &XWindow, geometry: XUpdateProp, display: XUpdateProp, debounce: bool) -> (Option<Vec<XUpdateProp>>, bool) {
+	// display changes bypass the debouncer, so crossing back and forth between displays
+	// within a single burst isn't swallowed
+	if debounce && window.matches(&display) {
+		return (None, true);
+	}
+
+	let changed = !window.matches(&geometry) || !window.matches(&display);
+
+	// still debounced so a pending trailing move can't report an older geometry
+	(changed.then(|| vec![geometry, display]), debounce)
+}
+
+/// Get the props to update for a debounced move or resize of a window, which are only its geometry,
+/// since the display was already updated right away and a debounced move may be older than that
+fn debounced_move_update(window: &XWindow, geometry: XUpdateProp) -> Option<Vec<XUpdateProp>> {
+	(!window.matches(&geometry)).then(|| vec![geometry])
+}
+
+/// Split a `WM_CLASS` value into its lowercased name and class, with spaces replaced by dashes
+fn parse_wm_class(value: &[u8]) -> (String, String) {
+	let value: Vec<u8> = value.iter()
+		.map(|b| if *b == b' ' { b'-' } else { b.to_ascii_lowercase() })
+		.collect();
+
+	// normally "name\0class\0", but some toolkits set a single (possibly unterminated) component,
+	// which is used for both rather than leaving the window untracked
+	let value = value.strip_suffix(&[0]).unwrap_or(&value[..]);
+
+	let (name, class) = match value.iter().position(|b| *b == 0) {
+		Some(sep) => (&value[..sep], &value[(sep+1)..]),
+		None => (value, value),
+	};
+
+	(String::from_utf8_lossy(name).into(), String::from_utf8_lossy(class).into())
+}
+
+/// Whether the X server supports looking up the pid of a window's client (X-Resource 1.2)
+async fn has_xres(conn: &RustConnection) -> bool {
+	if !matches!(conn.extension_information(res::X11_EXTENSION_NAME).await, Ok(Some(_))) {
+		return false;
+	}
+
+	let Ok(cookie) = conn.res_query_version(1, 2).await else {
+		return false;
+	};
+
+	cookie.reply().await.is_ok_and(|version| (version.server_major, version.server_minor) >= (1, 2))
+}
+
+async fn get_displays(conn: &RustConnection, roots: &[Window], connector_names: bool) -> Result<Vec<XDisplay>> {
 	let mut monitors: Vec<XDisplay> = Vec::new();
 
-	for m in reply.monitors {
-		let reply = conn.get_atom_name(m.name).await?.reply().await?;
-		let name: Box<str> = std::str::from_utf8(&reply.name)?.into();
+	for &root in roots {
+		let reply = conn.randr_get_monitors(root, true).await?.reply().await?;
+		let resources = conn.randr_get_screen_resources_current(root).await?.reply().await?;
+
+		for m in reply.monitors {
+			let reply = conn.get_atom_name(m.name).await?.reply().await?;
+			let mut name: Box<str> = std::str::from_utf8(&reply.name)?.into();
+			let refresh_rate = match m.outputs.first() {
+				Some(&output) => get_refresh_rate(conn, &resources, output).await.unwrap_or_default(),
+				None => 0.0,
+			};
+
+			// monitors can be named anything (e.g. with `xrandr --setmonitor`), while their outputs are named after the connector
+			if connector_names {
+				if let Some(&output) = m.outputs.first() {
+					name = get_output_name(conn, &resources, output).await.unwrap_or(name);
+				}
+			}
 
-		monitors.push(XDisplay {
-			name,
-			x: m.x,
-			y: m.y,
-			w: m.width as i16,
-			h: m.height as i16,
-		})
+			monitors.push(XDisplay {
+				root,
+				name,
+				x: m.x,
+				y: m.y,
+				w: m.width as i16,
+				h: m.height as i16,
+				primary: m.primary,
+				width_mm: m.width_in_millimeters,
+				refresh_rate,
+			})
+		}
 	}
 
 	Ok(monitors)
 }
 
+/// Find the master pointer of an XInput2 seat and the keyboard paired with it,
+/// where the seat is named after its master devices (e.g. "seat1" for "seat1 pointer" and "seat1 keyboard")
+async fn get_seat_devices(conn: &RustConnection, seat: &str) -> Result<(xinput::DeviceId, xinput::DeviceId)> {
+	conn.xinput_xi_query_version(2, 0).await?.reply().await?;
+
+	let devices = conn.xinput_xi_query_device(xinput::Device::ALL_MASTER).await?.reply().await?.infos;
+	let pointers = devices.iter().filter(|device| device.type_ == xinput::DeviceType::MASTER_POINTER);
+
+	let Some(pointer) = pointers.clone().find(|device| device.name == format!("{seat} pointer").as_bytes()) else {
+		let seats: Vec<_> = pointers
+			.filter_map(|device| std::str::from_utf8(&device.name).ok()?.strip_suffix(" pointer"))
+			.collect();
+
+		return Err(anyhow!("No XInput2 seat named \"{}\" (available seats: {})", seat, seats.join(", ")));
+	};
+
+	// the attachment of a master pointer is its paired master keyboard
+	Ok((pointer.deviceid, pointer.attachment))
+}
+
+/// Get the connector name of an output, e.g. `HDMI-A-1`
+async fn get_output_name(conn: &RustConnection, resources: &GetScreenResourcesCurrentReply, output: Output) -> Option<Box<str>> {
+	let output = conn.randr_get_output_info(output, resources.config_timestamp).await.ok()?.reply().await.ok()?;
+
+	Some(std::str::from_utf8(&output.name).ok()?.into())
+}
+
+/// Get the refresh rate of the mode currently used by the CRTC driving an output
+async fn get_refresh_rate(conn: &RustConnection, resources: &GetScreenResourcesCurrentReply, output: Output) -> Option<f64> {
+	let output = conn.randr_get_output_info(output, resources.config_timestamp).await.ok()?.reply().await.ok()?;
+	let crtc = conn.randr_get_crtc_info(output.crtc, resources.config_timestamp).await.ok()?.reply().await.ok()?;
+	let mode = resources.modes.iter().find(|mode| mode.id == crtc.mode)?;
+
+	let mut vtotal = mode.vtotal as f64;
+
+	if mode.mode_flags.contains(ModeFlag::DOUBLE_SCAN) {
+		vtotal *= 2.0;
+	}
+
+	if mode.mode_flags.contains(ModeFlag::INTERLACE) {
+		vtotal /= 2.0;
+	}
+
+	if mode.htotal == 0 || vtotal == 0.0 {
+		return None;
+	}
+
+	let rate = mode.dot_clock as f64 / (mode.htotal as f64 * vtotal);
+
+	Some((rate * 100.0).round() / 100.0)
+}
+
 struct X11<'a> {
 	conn: RustConnection,
+	/// Root window of the default screen
 	root: Window,
-	service: &'a ServiceProxy<'a>,
+	/// Root windows of all screens
+	roots: Vec<Window>,
+	sink: WindowSink<'a>,
 	atoms: Atoms,
 	window_types: HashMap<Atom, WindowType>,
+	window_states: HashMap<Atom, &'static str>,
+	hostname: Box<str>,
 	displays: Vec<XDisplay>,
+	display_index: DisplayIndex,
+	display_mode: DisplayMode,
+	/// Skip tracking the pointer window
+	active_only: bool,
+	/// Track override-redirect windows as the `override` window type
+	track_override: bool,
+	/// Prefer the title displayed by the window manager over the one set by the application
+	visible_name: bool,
+	/// Name displays by their output connector rather than their monitor name
+	connector_names: bool,
+	/// Master pointer and keyboard of the XInput2 seat to resolve windows with, instead of the core devices
+	seat: Option<(xinput::DeviceId, xinput::DeviceId)>,
+	/// Whether the X-Resource extension can be used to get the pid of windows without `_NET_WM_PID`
+	has_xres: bool,
 	active_window: XWindow,
 	pointer_window: XWindow,
 }
 
 impl<'a> X11<'a> {
-	async fn new(service: &'a ServiceProxy<'_>) -> Result<Self> {
+	async fn new(sink: WindowSink<'a>, display_mode: DisplayMode, active_only: bool, track_override: bool, visible_name: bool, connector_names: bool, seat: Option<&str>) -> Result<Self> {
 		let (conn, screen_num, drive) = RustConnection::connect(None).await?;
-		let root = conn.setup().roots[screen_num].root;
+		let roots: Vec<Window> = conn.setup().roots.iter().map(|screen| screen.root).collect();
+		let root = roots[screen_num];
 
 		tokio::spawn(async move {
 			match drive.await {
@@ -224,22 +702,56 @@ impl<'a> X11<'a> {
 		concurrent!(
 			let atoms = Atoms::load(&conn),
 			let window_types = Atoms::load_window_types(&conn),
-			let displays = get_displays(&conn, root),
+			let window_states = Atoms::load_window_states(&conn),
+			let displays = get_displays(&conn, &roots, connector_names),
+			let has_xres = has_xres(&conn),
 		);
 
+		let seat = match seat {
+			Some(seat) => Some(get_seat_devices(&conn, seat).await?),
+			None => None,
+		};
+
 		Ok(X11 {
 			conn,
 			root,
-			service,
+			roots,
+			sink,
 			atoms: atoms?,
 			window_types: window_types?,
+			window_states: window_states?,
+			hostname: std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default().trim().into(),
+			display_index: DisplayIndex::new(displays.as_deref().unwrap_or_default()),
 			displays: displays?,
+			display_mode,
+			active_only,
+			track_override,
+			visible_name,
+			connector_names,
+			seat,
+			has_xres,
 			active_window: XWindow::default(),
 			pointer_window: XWindow::default(),
 		})
 	}
 
 	async fn set_window(&mut self, context: WindowContext, window: XWindow) -> Result<()> {
+		let (client, user_time) = windows_to_select(&window, &self.active_window, &self.pointer_window);
+
+		if let Some(client) = client {
+			if self.conn.change_window_attributes(client, &window_event_mask(self.active_only)).await.is_ok() {
+				self.conn.flush().await?;
+			}
+		}
+
+		if let Some(user_time) = user_time {
+			let event_mask = ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE);
+
+			if self.conn.change_window_attributes(user_time, &event_mask).await.is_ok() {
+				self.conn.flush().await?;
+			}
+		}
+
 		let window = match context {
 			WindowContext::Active => {
 				self.active_window = window;
@@ -256,24 +768,65 @@ impl<'a> X11<'a> {
 			}
 		};
 
-		self.service.windows.set_window(context, window.as_map()).await.map_err(Into::into)
+		self.sink.set_window(context, window.as_dict()).await
 	}
 
-	async fn update_window(&mut self, mut context: WindowContext, prop: XUpdateProp) -> Result<()> {
-		if context == WindowContext::Active && self.active_window.id == self.pointer_window.id {
+	async fn update_window(&mut self, mut context: WindowContext, props: Vec<XUpdateProp>) -> Result<()> {
+		if context == WindowContext::Active && !self.active_only && self.active_window.id == self.pointer_window.id {
 			context = WindowContext::Both;
 		}
 
-		let (key, value) = match context {
-			WindowContext::Active => self.active_window.update(prop),
-			WindowContext::Pointer => self.pointer_window.update(prop),
+		let values: Vec<_> = props.iter().flat_map(XUpdateProp::values).collect();
+
+		let window = match context {
+			WindowContext::Active => {
+				props.into_iter().for_each(|prop| self.active_window.update(prop));
+				&self.active_window
+			},
+			WindowContext::Pointer => {
+				props.into_iter().for_each(|prop| self.pointer_window.update(prop));
+				&self.pointer_window
+			},
 			WindowContext::Both => {
-				self.active_window.update(prop.clone());
-				self.pointer_window.update(prop)
+				for prop in props {
+					self.active_window.update(prop.clone());
+					self.pointer_window.update(prop);
+				}
+				&self.active_window
 			},
 		};
 
-		self.service.windows.update_window(context, key, value).await.map_err(Into::into)
+		self.sink.update_window(context, values, || window.as_dict()).await
+	}
+
+	async fn send_displays(&self) -> Result<()> {
+		let displays = self.displays.iter().map(|d| DisplayDict {
+			name: d.name.to_string(),
+			x: d.x.into(),
+			y: d.y.into(),
+			width: d.w as u32,
+			height: d.h as u32,
+			primary: d.primary,
+			// X11 has no per-monitor scaling
+			scale: 1.0,
+			dpi: match d.width_mm {
+				0 => 0,
+				mm => (d.w as f64 * 25.4 / mm as f64).round() as u32,
+			},
+			refresh_rate: d.refresh_rate,
+		}).collect();
+
+		self.sink.set_displays(displays).await
+	}
+
+	async fn update_window_prop(&mut self, win_id: Window, prop: XUpdateProp) -> Result<()> {
+		if win_id == self.active_window.id && !self.active_window.matches(&prop) {
+			self.update_window(WindowContext::Active, vec![prop]).await
+		} else if win_id == self.pointer_window.id && !self.pointer_window.matches(&prop) {
+			self.update_window(WindowContext::Pointer, vec![prop]).await
+		} else {
+			Ok(())
+		}
 	}
 
 	async fn get_window(&self, top_id: Window, win_match: PartialMatch) -> XWindow {
@@ -282,35 +835,166 @@ impl<'a> X11<'a> {
 		}
 
 		let id = win_match.0;
+		let root = self.get_window_root(id).await;
 
 		concurrent!(
 			let pid = self.get_window_pid(id),
+			let desktop_id = self.get_window_desktop_id(id),
+			let is_local = self.is_local_window(id),
 			let title = self.get_window_title(id),
 			let r#type = self.get_window_type(id),
 			let role = self.get_window_role(id),
 			let state = self.get_window_state(id),
+			let decorated = self.get_window_decorated(id),
+			let accepts_focus = self.get_window_accepts_focus(id),
 			let display = self.get_window_display(id),
+			let stacking = self.get_stacking_order(root),
+			let geometry = self.get_window_geometry(top_id),
+			let user_time = self.get_window_user_time(id),
+			let visual = self.get_window_visual(id),
+			let override_redirect = self.is_override_redirect(top_id),
+			let leader = self.get_window_leader(id),
+			let workspace = self.get_window_workspace(id),
 		);
 
-		XWindow::new(
+		let pid = pid.unwrap_or_default();
+		let comm = if is_local { process_comm(pid) } else { None };
+
+		let mut window = XWindow::new(
 			win_match,
 			top_id,
-			pid.unwrap_or_default(),
+			pid,
+			comm.unwrap_or_default().into(),
 			title.unwrap_or_default(),
-			r#type.unwrap_or_default(),
+			if override_redirect { WindowType::Override } else { r#type.unwrap_or_default() },
 			role.unwrap_or_default(),
-			state.unwrap_or_default(),
-			display.unwrap_or_default(),
-		)
+			self.display_prop(display.unwrap_or_default()),
+		);
+
+		window.root = root;
+		window.icon = desktop_id.unwrap_or_default();
+		window.update(XUpdateProp::Decorated(decorated));
+		let (state, states) = state.unwrap_or_default();
+		window.update(XUpdateProp::State(state, states));
+		window.user_time_id = user_time.0;
+		// the visual, group leader and focus model can't change, so they're only read once
+		(window.depth, window.visual) = visual.unwrap_or_default();
+		window.leader = leader.unwrap_or_default();
+		window.accepts_focus = accepts_focus;
+		window.update(XUpdateProp::UserTime(user_time.1.unwrap_or_default()));
+		window.update(XUpdateProp::Workspace(workspace.unwrap_or_default()));
+		window.update(window.stack_prop(&stacking.unwrap_or_default()));
+
+		if let Some(geometry) = geometry {
+			window.update(geometry);
+		}
+
+		window
 	}
 
-	async fn query_active_window(&self) -> Option<XWindow> {
-		let win_id = self.get_window_prop(self.root, self.atoms.ACTIVE_WINDOW, AtomEnum::WINDOW).await?.value32()?.next()?;
+	/// Get the root window of the screen a window is on
+	async fn get_window_root(&self, win_id: Window) -> Window {
+		if self.roots.len() == 1 {
+			return self.root;
+		}
+
+		match self.conn.get_geometry(win_id).await {
+			Ok(cookie) => cookie.reply().await.map_or(self.root, |geometry| geometry.root),
+			Err(_) => self.root,
+		}
+	}
+
+	/// Get the top-level window containing a window, which is the window manager's frame for reparented windows
+	async fn get_top_window(&self, mut win_id: Window) -> Option<Window> {
+		loop {
+			let tree = self.conn.query_tree(win_id).await.ok()?.reply().await.ok()?;
+
+			if tree.parent == tree.root || tree.parent == 0 {
+				return Some(win_id);
+			}
+
+			win_id = tree.parent;
+		}
+	}
+
+	/// Raise or lower a window, or the active window if the id is empty
+	async fn restack_window(&self, id: &str, mode: StackMode) -> Result<()> {
+		let win_id = match id {
+			"" if self.active_window.id == 0 => return Err(anyhow!("There is no active window")),
+			"" => self.active_window.id,
+			id => id.parse().map_err(|_| anyhow!("Invalid window ID: {id}"))?,
+		};
+
+		let Some(top_id) = self.get_top_window(win_id).await else {
+			return Err(anyhow!("Window {win_id} can't be restacked"));
+		};
+
+		let root = self.get_window_root(win_id).await;
+		let supported = self.get_window_prop(root, self.atoms.SUPPORTED, AtomEnum::ATOM).await
+			.and_then(|reply| reply.value32().map(|mut atoms| atoms.any(|atom| atom == self.atoms.RESTACK_WINDOW)))
+			.unwrap_or_default();
+
+		if supported {
+			// ask the window manager, so it can apply its own stacking policy (e.g. keeping docks above other windows)
+			let event = ClientMessageEvent::new(32, win_id, self.atoms.RESTACK_WINDOW, [2, 0, u32::from(mode), 0, 0]);
+			self.conn.send_event(false, root, EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY, event).await?.check().await
+				.map_err(|_| anyhow!("Window {win_id} can't be restacked"))?;
+		} else {
+			self.conn.configure_window(top_id, &ConfigureWindowAux::new().stack_mode(mode)).await?.check().await
+				.map_err(|_| anyhow!("Window {win_id} can't be restacked"))?;
+		}
+
+		self.conn.flush().await?;
+
+		Ok(())
+	}
+
+	async fn get_active_window_id(&self, root: Window) -> Option<Window> {
+		let win_id = self.get_window_prop(root, self.atoms.ACTIVE_WINDOW, AtomEnum::WINDOW).await?.value32()?.next()?;
 
 		if win_id == 0 {
 			return None;
 		}
 
+		Some(win_id)
+	}
+
+	async fn query_active_window(&self) -> Option<XWindow> {
+		// the EWMH active window is shared by every seat, so a seat uses the focus of its own keyboard
+		if self.seat.is_some() {
+			return self.query_focus_window().await;
+		}
+
+		// prefer the default screen, but the active window may be on any of them
+		let mut win_id = None;
+
+		for root in std::iter::once(self.root).chain(self.roots.iter().copied().filter(|root| *root != self.root)) {
+			win_id = self.get_active_window_id(root).await;
+
+			if win_id.is_some() {
+				break;
+			}
+		}
+
+		let win_id = win_id?;
+		let win_match = self.resolve_window_match(win_id).await?;
+		let window = self.get_window(win_id, win_match).await;
+
+		Some(window)
+	}
+
+	/// Get the window with the input focus from the X server, rather than the window manager's `_NET_ACTIVE_WINDOW`
+	async fn query_focus_window(&self) -> Option<XWindow> {
+		let win_id = match self.seat {
+			Some((_, keyboard)) => self.conn.xinput_xi_get_focus(keyboard).await.ok()?.reply().await.ok()?.focus,
+			None => self.conn.get_input_focus().await.ok()?.reply().await.ok()?.focus,
+		};
+
+		// no window or the root window (including PointerRoot) has the focus
+		if win_id == 0 || win_id == u32::from(InputFocus::POINTER_ROOT) || self.roots.contains(&win_id) {
+			return None;
+		}
+
 		let win_match = self.resolve_window_match(win_id).await?;
 		let window = self.get_window(win_id, win_match).await;
 
@@ -318,7 +1002,26 @@ impl<'a> X11<'a> {
 	}
 
 	async fn query_pointer_window(&self) -> Option<XWindow> {
-		let win_id = self.conn.query_pointer(self.root).await.ok()?.reply().await.ok()?.child;
+		let mut win_id = 0;
+
+		// the pointer is only on one screen at a time
+		for &root in &self.roots {
+			let (same_screen, child) = match self.seat {
+				Some((pointer, _)) => {
+					let reply = self.conn.xinput_xi_query_pointer(root, pointer).await.ok()?.reply().await.ok()?;
+					(reply.same_screen, reply.child)
+				}
+				None => {
+					let reply = self.conn.query_pointer(root).await.ok()?.reply().await.ok()?;
+					(reply.same_screen, reply.child)
+				}
+			};
+
+			if same_screen {
+				win_id = child;
+				break;
+			}
+		}
 
 		if win_id == 0 {
 			return None;
@@ -331,7 +1034,12 @@ impl<'a> X11<'a> {
 	}
 
 	async fn cascade_event_mask(&self, win_id: Window, event_mask: &ChangeWindowAttributesAux) -> Result<bool> {
-		if win_id == 0 {
+		self.cascade_event_mask_inner(win_id, event_mask, &mut HashSet::new()).await
+	}
+
+	async fn cascade_event_mask_inner(&self, win_id: Window, event_mask: &ChangeWindowAttributesAux, visited: &mut HashSet<Window>) -> Result<bool> {
+		// guard against revisiting windows if the tree changes (e.g. reparenting) while we walk it
+		if win_id == 0 || !visited.insert(win_id) {
 			return Ok(false);
 		}
 
@@ -343,7 +1051,7 @@ impl<'a> X11<'a> {
 		}
 
 		for child_id in self.conn.query_tree(win_id).await?.reply().await?.children {
-			if Box::pin(self.cascade_event_mask(child_id, &event_mask)).await? {
+			if Box::pin(self.cascade_event_mask_inner(child_id, event_mask, visited)).await? {
 				return Ok(true);
 			}
 		}
@@ -352,7 +1060,11 @@ impl<'a> X11<'a> {
 	}
 
 	async fn resolve_window_match(&self, win_id: Window) -> Option<PartialMatch> {
-		if win_id == 0 {
+		self.resolve_window_match_inner(win_id, &mut HashSet::new()).await
+	}
+
+	async fn resolve_window_match_inner(&self, win_id: Window, visited: &mut HashSet<Window>) -> Option<PartialMatch> {
+		if win_id == 0 || !visited.insert(win_id) {
 			return None;
 		}
 
@@ -361,7 +1073,7 @@ impl<'a> X11<'a> {
 		}
 
 		for child in self.conn.query_tree(win_id).await.ok()?.reply().await.ok()?.children {
-			if let found @ Some(_) = Box::pin(self.resolve_window_match(child)).await {
+			if let found @ Some(_) = Box::pin(self.resolve_window_match_inner(child, visited)).await {
 				return found;
 			}
 		}
@@ -376,25 +1088,7 @@ impl<'a> X11<'a> {
 			return None
 		}
 
-		let mut value = reply.value;
-		let mut sep = None;
-
-		for (i, b) in value.iter_mut().enumerate() {
-			if *b == 0 {
-				if sep == None {
-					sep = Some(i);
-				}
-			} else if *b == b' ' {
-				*b = b'-';
-			} else {
-				b.make_ascii_lowercase();
-			}
-		}
-
-		let sep = sep?;
-
-		let name = std::str::from_utf8(&value[0..sep]).ok()?.into();
-		let class = std::str::from_utf8(&value[(sep+1)..(value.len()-1)]).ok()?.into();
+		let (name, class) = parse_wm_class(&reply.value);
 
 		Some((win_id, name, class))
 	}
@@ -404,12 +1098,19 @@ impl<'a> X11<'a> {
 		A: Into<Atom> + Send + 'static,
 		B: Into<Atom> + Send + 'static,
 	{
-		let reply = self.conn.get_property(false, win_id, atom_prop, atom_type, 0, 1024).await.ok()?.reply().await.ok()?;
+		let (atom_prop, atom_type): (Atom, Atom) = (atom_prop.into(), atom_type.into());
+		let mut reply = self.conn.get_property(false, win_id, atom_prop, atom_type, 0, PROP_READ_LEN).await.ok()?.reply().await.ok()?;
 
 		if reply.value_len == 0 {
 			return None
 		}
 
+		// read the whole value if it didn't fit in the initial read (e.g. very long titles)
+		if reply.bytes_after > 0 {
+			let long_length = (reply.value.len() as u32 + reply.bytes_after).div_ceil(4).min(PROP_MAX_LEN);
+			reply = self.conn.get_property(false, win_id, atom_prop, atom_type, 0, long_length).await.ok()?.reply().await.ok()?;
+		}
+
 		Some(reply)
 	}
 
@@ -424,20 +1125,149 @@ impl<'a> X11<'a> {
 	}
 
 	async fn get_window_pid(&self, win_id: Window) -> Option<u32> {
-		let reply = self.get_window_prop(win_id, self.atoms.WM_PID, AtomEnum::CARDINAL).await?;
+		if let Some(pid) = self.get_window_prop(win_id, self.atoms.WM_PID, AtomEnum::CARDINAL).await.and_then(|reply| reply.value32()?.next()) {
+			return Some(pid);
+		}
+
+		// some apps (e.g. Wine) don't set the property, but the X server knows the pid of the local client owning the window
+		if !self.has_xres {
+			return None;
+		}
+
+		let spec = res::ClientIdSpec { client: win_id, mask: res::ClientIdMask::LOCAL_CLIENT_PID };
+		let reply = self.conn.res_query_client_ids(&[spec]).await.ok()?.reply().await.ok()?;
+
+		reply.ids.into_iter()
+			.find(|id| id.spec.mask.contains(res::ClientIdMask::LOCAL_CLIENT_PID))
+			.and_then(|id| id.value.first().copied())
+	}
+
+	/// Whether the window belongs to a client on this host, so that its pid refers to a local process
+	async fn is_local_window(&self, win_id: Window) -> bool {
+		let Some(reply) = self.get_window_prop(win_id, AtomEnum::WM_CLIENT_MACHINE, AtomEnum::STRING).await else {
+			return true;
+		};
+
+		let machine = std::str::from_utf8(&reply.value).unwrap_or_default().trim_end_matches('\0');
+
+		machine == &*self.hostname
+	}
+
+	/// Get the id of the application's desktop entry, if the toolkit sets it
+	async fn get_window_desktop_id(&self, win_id: Window) -> Option<Box<str>> {
+		let mut result = self.get_window_prop(win_id, self.atoms.GTK_APPLICATION_ID, self.atoms.UTF8_STRING).await;
+
+		if result.is_none() {
+			result = self.get_window_prop(win_id, self.atoms.KDE_DESKTOP_FILE, self.atoms.UTF8_STRING).await;
+		}
+
+		Some(std::str::from_utf8(&result?.value).ok()?.into())
+	}
+
+	/// Get the window holding the user time and its value, from the window itself or its `_NET_WM_USER_TIME_WINDOW`
+	async fn get_window_user_time(&self, win_id: Window) -> (Window, Option<u32>) {
+		let user_time_id = match self.get_window_prop(win_id, self.atoms.WM_USER_TIME_WINDOW, AtomEnum::WINDOW).await {
+			Some(reply) => reply.value32().and_then(|mut value| value.next()).filter(|&id| id != 0).unwrap_or(win_id),
+			None => win_id,
+		};
+
+		(user_time_id, self.get_user_time(user_time_id).await)
+	}
+
+	async fn get_user_time(&self, win_id: Window) -> Option<u32> {
+		let reply = self.get_window_prop(win_id, self.atoms.WM_USER_TIME, AtomEnum::CARDINAL).await?;
 		let value = reply.value32()?.next()?;
 
 		Some(value)
 	}
 
+	/// Whether the window manager decorates the window, which clients opt out of with Motif hints or by drawing
+	/// their own decorations (advertised with `_GTK_FRAME_EXTENTS`), and is otherwise the default
+	async fn get_window_decorated(&self, win_id: Window) -> bool {
+		if self.get_window_prop(win_id, self.atoms.GTK_FRAME_EXTENTS, AtomEnum::CARDINAL).await.is_some() {
+			return false;
+		}
+
+		let Some(reply) = self.get_window_prop(win_id, self.atoms.MOTIF_WM_HINTS, AtomEnum::ANY).await else {
+			return true;
+		};
+
+		// flags, functions, decorations, input mode, status
+		let hints: Vec<u32> = reply.value32().map(|value| value.collect()).unwrap_or_default();
+
+		match hints.as_slice() {
+			[flags, _, decorations, ..] if flags & MWM_HINTS_DECORATIONS != 0 => *decorations != 0,
+			_ => true,
+		}
+	}
+
+	/// Whether the window accepts keyboard focus, which clients opt out of by setting the input hint in `WM_HINTS`
+	/// to false without supporting `WM_TAKE_FOCUS`, and is otherwise assumed (like window managers do)
+	async fn get_window_accepts_focus(&self, win_id: Window) -> bool {
+		if let Some(reply) = self.get_window_prop(win_id, self.atoms.WM_PROTOCOLS, AtomEnum::ATOM).await {
+			if reply.value32().is_some_and(|mut protocols| protocols.any(|atom| atom == self.atoms.WM_TAKE_FOCUS)) {
+				return true;
+			}
+		}
+
+		let Some(reply) = self.get_window_prop(win_id, AtomEnum::WM_HINTS, AtomEnum::WM_HINTS).await else {
+			return true;
+		};
+
+		// flags, input, ...
+		let hints: Vec<u32> = reply.value32().map(|value| value.take(2).collect()).unwrap_or_default();
+
+		match hints.as_slice() {
+			[flags, input] if flags & WM_HINTS_INPUT != 0 => *input != 0,
+			_ => true,
+		}
+	}
+
+	/// Get the name of the desktop a window is on from `_NET_DESKTOP_NAMES`, falling back to its number (from 1),
+	/// or an empty name for windows on all desktops
+	async fn get_window_workspace(&self, win_id: Window) -> Option<Box<str>> {
+		let desktop = self.get_window_prop(win_id, self.atoms.WM_DESKTOP, AtomEnum::CARDINAL).await?.value32()?.next()?;
+
+		if desktop == ALL_DESKTOPS {
+			return Some("".into());
+		}
+
+		let root = self.get_window_root(win_id).await;
+		let names = self.get_window_prop(root, self.atoms.DESKTOP_NAMES, self.atoms.UTF8_STRING).await;
+
+		// names are NUL-terminated, and there can be fewer names than desktops
+		let name = names.and_then(|reply| {
+			let name = reply.value.split(|b| *b == 0).nth(desktop as usize)?;
+			Some(std::str::from_utf8(name).ok()?.into()).filter(|name: &Box<str>| !name.is_empty())
+		});
+
+		Some(name.unwrap_or_else(|| (desktop + 1).to_string().into()))
+	}
+
+	async fn get_window_leader(&self, win_id: Window) -> Option<Window> {
+		let reply = self.get_window_prop(win_id, self.atoms.WM_CLIENT_LEADER, AtomEnum::WINDOW).await?;
+
+		reply.value32()?.next()
+	}
+
 	async fn get_window_title(&self, win_id: Window) -> Option<Box<str>> {
-		let result = self.get_window_prop(win_id, self.atoms.WM_NAME, self.atoms.UTF8_STRING).await;
+		// the visible name is what the window manager actually displays (e.g. with a "[2]" suffix for duplicate titles)
+		if self.visible_name {
+			if let Some(reply) = self.get_window_prop(win_id, self.atoms.WM_VISIBLE_NAME, self.atoms.UTF8_STRING).await {
+				if let Ok(title) = std::str::from_utf8(&reply.value) {
+					return Some(title.into());
+				}
+			}
+		}
 
-		/*if result.is_none() {
-			result = self.get_window_prop(win_id, AtomEnum::WM_NAME, AtomEnum::STRING).await;
-		}*/
+		if let Some(reply) = self.get_window_prop(win_id, self.atoms.WM_NAME, self.atoms.UTF8_STRING).await {
+			return Some(std::str::from_utf8(&reply.value).ok()?.into());
+		}
 
-		Some(std::str::from_utf8(&result?.value).ok()?.into())
+		// the legacy name is Latin-1, which maps directly to the first 256 code points
+		let reply = self.get_window_prop(win_id, AtomEnum::WM_NAME, AtomEnum::STRING).await?;
+
+		Some(reply.value.iter().map(|&b| b as char).collect::<String>().into())
 	}
 
 	async fn get_window_type(&self, win_id: Window) -> Option<WindowType> {
@@ -455,126 +1285,323 @@ impl<'a> X11<'a> {
 		Some(std::str::from_utf8(&result?.value).ok()?.into())
 	}
 
-	async fn get_window_state(&self, win_id: Window) -> Option<WindowState> {
+	/// Get the window state, and the names of all its `_NET_WM_STATE` atoms (e.g. `SKIP_TASKBAR,ABOVE`)
+	async fn get_window_state(&self, win_id: Window) -> Option<(WindowState, Box<str>)> {
 		let reply = self.get_window_prop(win_id, self.atoms.WM_STATE, AtomEnum::ATOM).await?;
-		let states: HashSet<u32> = reply.value32()?.collect();
+		let atoms: Vec<u32> = reply.value32()?.collect();
+		let states: HashSet<u32> = atoms.iter().copied().collect();
 
-		if states.contains(&self.atoms.WM_STATE_FULLSCREEN) {
-			Some(WindowState::Fullscreen)
+		let state = if states.contains(&self.atoms.WM_STATE_FULLSCREEN) {
+			WindowState::Fullscreen
 		} else if states.contains(&self.atoms.WM_STATE_MAXIMIZED_HORZ) && states.contains(&self.atoms.WM_STATE_MAXIMIZED_VERT) {
-			Some(WindowState::Maximized)
+			WindowState::Maximized
+		} else if states.contains(&self.atoms.WM_STATE_SHADED) {
+			WindowState::Shaded
 		} else {
-			Some(WindowState::Normal)
+			WindowState::Normal
+		};
+
+		let names = atoms.iter()
+			.filter_map(|atom| self.window_states.get(atom).copied())
+			.collect::<Vec<_>>()
+			.join(",");
+
+		Some((state, names.into()))
+	}
+
+	/// Get the geometry of a top level (frame) window relative to the root window
+	async fn get_window_geometry(&self, win_id: Window) -> Option<XUpdateProp> {
+		let geometry = self.conn.get_geometry(win_id).await.ok()?.reply().await.ok()?;
+		let translate = self.conn.translate_coordinates(win_id, geometry.root, 0, 0).await.ok()?.reply().await.ok()?;
+
+		Some(XUpdateProp::Geometry(translate.dst_x.into(), translate.dst_y.into(), geometry.width.into(), geometry.height.into()))
+	}
+
+	/// Get the depth and visual id of a client window
+	async fn get_window_visual(&self, win_id: Window) -> Option<(u32, u32)> {
+		let (geometry, attributes) = tokio::join!(
+			async { self.conn.get_geometry(win_id).await.ok()?.reply().await.ok() },
+			async { self.conn.get_window_attributes(win_id).await.ok()?.reply().await.ok() },
+		);
+
+		Some((geometry?.depth.into(), attributes?.visual))
+	}
+
+	/// Whether a window bypasses the window manager, only checked when tracking override-redirect windows
+	async fn is_override_redirect(&self, win_id: Window) -> bool {
+		if !self.track_override {
+			return false;
 		}
+
+		let Ok(cookie) = self.conn.get_window_attributes(win_id).await else {
+			return false;
+		};
+
+		cookie.reply().await.is_ok_and(|attributes| attributes.override_redirect)
 	}
 
-	async fn get_window_display(&self, win_id: Window) -> Option<Box<str>> {
+	async fn get_window_display(&self, win_id: Window) -> Option<Vec<usize>> {
 		let geometry = self.conn.get_geometry(win_id).await.ok()?.reply().await.ok()?;
-		let translate = self.conn.translate_coordinates(win_id, self.root, geometry.x, geometry.y).await.ok()?.reply().await.ok()?;
+		let translate = self.conn.translate_coordinates(win_id, geometry.root, geometry.x, geometry.y).await.ok()?.reply().await.ok()?;
 
-		self.calc_window_display(translate.dst_x, translate.dst_y, geometry.width, geometry.height)
+		Some(self.calc_window_display(geometry.root, translate.dst_x, translate.dst_y, geometry.width, geometry.height))
 	}
 
-	fn calc_window_display(&self, x: i16, y: i16, w: u16, h: u16) -> Option<Box<str>> {
+	async fn get_stacking_order(&self, root: Window) -> Option<Vec<Window>> {
+		let reply = self.get_window_prop(root, self.atoms.CLIENT_LIST_STACKING, AtomEnum::WINDOW).await?;
+
+		Some(reply.value32()?.collect())
+	}
+
+	/// Get the indexes of the displays a window is on, only considering the displays of the window's screen
+	fn calc_window_display(&self, root: Window, x: i16, y: i16, w: u16, h: u16) -> Vec<usize> {
 		let w = w as i16;
 		let h = h as i16;
 
-		// first try to find monitor containing the center point
-		let cx = x + (w / 2);
-		let cy = y + (h / 2);
-
-		if let Some(d) = self.displays.iter().find(|d| {
-			cx >= d.x
-			&& cx < d.x + d.w
-			&& cy >= d.y
-			&& cy < d.y + d.h
-		}) {
-			return Some(d.name.clone());
+		if let DisplayMode::Center = self.display_mode {
+			// first try to find monitor containing the center point
+			let cx = x + (w / 2);
+			let cy = y + (h / 2);
+
+			// candidates aren't in display order, so take the first display containing it like a full scan would
+			if let Some(i) = self.display_index.candidates(&self.displays, root, cx as i32, cx as i32 + 1).filter(|&i| {
+				let d = &self.displays[i];
+
+				cx >= d.x
+				&& cx < d.x + d.w
+				&& cy >= d.y
+				&& cy < d.y + d.h
+			}).min() {
+				return vec![i];
+			}
 		}
 
-		// if center point isn't on any display, find the one with the most window overlap
-		let mut matched = None;
+		// find the displays overlapping the window, or just the one with the most overlap
+		let mut matched = Vec::new();
 		let mut max_overlap_area = 0;
 
-		for d in self.displays.iter() {
+		for i in self.display_index.candidates(&self.displays, root, x as i32, x as i32 + w as i32) {
+			let d = &self.displays[i];
+
 			let over_x1 = i16::max(x, d.x);
 			let over_y1 = i16::max(y, d.y);
 			let over_x2 = i16::min(x + w, d.x + d.w);
 			let over_y2 = i16::min(y + h, d.y + d.h);
 
 			if over_x1 < over_x2 && over_y1 < over_y2 {
+				if let DisplayMode::All = self.display_mode {
+					matched.push(i);
+					continue;
+				}
+
 				let overlap_area = (over_x2 - over_x1) as u32 * (over_y2 - over_y1) as u32;
 
-				if overlap_area > max_overlap_area {
+				// ties go to the first display, regardless of the order of the candidates
+				if overlap_area > max_overlap_area || (overlap_area == max_overlap_area && matched.first().is_some_and(|&m| i < m)) {
 					max_overlap_area = overlap_area;
-					matched = Some(d.name.clone());
+					matched = vec![i];
 				}
 			}
 		}
 
+		matched.sort_unstable();
 		matched
 	}
+
+	fn display_prop(&self, indexes: Vec<usize>) -> XUpdateProp {
+		let displays: Vec<_> = indexes.into_iter()
+			.filter_map(|i| self.displays.get(i).map(|d| (i, d)))
+			.collect();
+
+		match displays.first() {
+			Some((i, _)) => XUpdateProp::Display(
+				displays.iter().map(|(_, d)| &*d.name).collect::<Vec<_>>().join(",").into(),
+				*i as u32,
+			),
+			None => XUpdateProp::Display(Default::default(), 0),
+		}
+	}
 }
 
 #[derive(Clone, Debug)]
 enum XUpdateProp {
 	Title(Box<str>),
-	State(WindowState),
-	Display(Box<str>),
-	// TODO: Are any other properties likely to change?
+	State(WindowState, Box<str>),
+	Decorated(bool),
+	Display(Box<str>, u32),
+	Class(Box<str>, Box<str>),
+	Type(WindowType),
+	Role(Box<str>),
+	Stack(i32),
+	UserTime(u32),
+	Workspace(Box<str>),
+	Geometry(i32, i32, u32, u32),
+}
+
+impl XUpdateProp {
+	/// The window properties and their values to send for the update
+	fn values(&self) -> Vec<(WindowProp, String)> {
+		match self {
+			Self::Title(value) => vec![(WindowProp::Title, value.to_string())],
+			Self::State(value, states) => vec![(WindowProp::State, value.to_string()), (WindowProp::States, states.to_string())],
+			Self::Decorated(value) => vec![(WindowProp::Decorated, value.to_string())],
+			Self::Display(name, index) => vec![(WindowProp::Display, name.to_string()), (WindowProp::DisplayIndex, index.to_string())],
+			Self::Class(name, class) => vec![(WindowProp::Name, name.to_string()), (WindowProp::Class, class.to_string())],
+			Self::Type(value) => vec![(WindowProp::Type, value.to_string())],
+			Self::Role(value) => vec![(WindowProp::Role, value.to_string())],
+			Self::Stack(value) => vec![(WindowProp::Stack, value.to_string())],
+			Self::UserTime(value) => vec![(WindowProp::UserTime, value.to_string())],
+			Self::Workspace(value) => vec![(WindowProp::Workspace, value.to_string())],
+			Self::Geometry(x, y, width, height) => vec![
+				(WindowProp::X, x.to_string()),
+				(WindowProp::Y, y.to_string()),
+				(WindowProp::Width, width.to_string()),
+				(WindowProp::Height, height.to_string()),
+			],
+		}
+	}
 }
 
 #[derive(Clone, Debug)]
 struct XWindow {
 	id: Window,
 	top_id: Window,
+	root: Window,
 	name: Box<str>,
 	class: Box<str>,
+	/// Desktop entry id, resolved to an icon name by the daemon
+	icon: Box<str>,
 	pid: u32,
+	comm: Box<str>,
 	title: Box<str>,
 	r#type: WindowType,
 	role: Box<str>,
+	/// Group leader from `WM_CLIENT_LEADER`, or 0 if none
+	leader: Window,
 	state: WindowState,
+	states: Box<str>,
+	decorated: bool,
+	accepts_focus: bool,
 	display: Box<str>,
+	display_index: u32,
+	x: i32,
+	y: i32,
+	width: u32,
+	height: u32,
+	workspace: Box<str>,
+	stack: i32,
+	user_time: u32,
+	depth: u32,
+	visual: u32,
+	/// Window holding `_NET_WM_USER_TIME`, which is a separate `_NET_WM_USER_TIME_WINDOW` for some clients
+	user_time_id: Window,
 }
 
 impl XWindow {
-	fn new(win_match: PartialMatch, top_id: Window, pid: u32, title: Box<str>, r#type: WindowType, role: Box<str>, state: WindowState, display: Box<str>) -> Self {
+	fn new(win_match: PartialMatch, top_id: Window, pid: u32, comm: Box<str>, title: Box<str>, r#type: WindowType, role: Box<str>, display: XUpdateProp) -> Self {
 		let (id, name, class) = win_match;
 
-		Self {
+		let mut window = Self {
 			id,
 			top_id,
+			root: 0,
 			name,
 			class,
+			icon: Default::default(),
 			pid,
+			comm,
 			title,
 			r#type,
 			role,
-			state,
-			display,
-		}
+			leader: 0,
+			state: WindowState::Normal,
+			states: Default::default(),
+			decorated: true,
+			accepts_focus: true,
+			display: Default::default(),
+			display_index: 0,
+			x: 0,
+			y: 0,
+			width: 0,
+			height: 0,
+			workspace: Default::default(),
+			stack: -1,
+			user_time: 0,
+			depth: 0,
+			visual: 0,
+			user_time_id: id,
+		};
+
+		window.update(display);
+		window
 	}
 
-	fn as_map(&self) -> DictMap {
+	fn as_dict(&self) -> WindowDict {
 		WindowDict::new(
 			&self.id.to_string(),
 			&self.name,
 			&self.class,
+			&self.icon,
 			self.pid,
+			&self.comm,
 			&self.title,
 			self.r#type,
 			&self.role,
+			&if self.leader != 0 { self.leader.to_string() } else { String::new() },
 			self.state,
-			&self.display
-		).into()
+			&self.states,
+			self.decorated,
+			self.accepts_focus,
+			&self.display,
+			self.display_index,
+			self.x,
+			self.y,
+			self.width,
+			self.height,
+			&self.workspace,
+			self.stack,
+			self.user_time,
+			self.depth,
+			self.visual,
+		)
+	}
+
+	/// Get the window's position in the stacking order, from either its client or frame window
+	fn stack_prop(&self, stacking: &[Window]) -> XUpdateProp {
+		let position = stacking.iter().position(|w| *w == self.id || *w == self.top_id);
+
+		XUpdateProp::Stack(position.map_or(-1, |i| i as i32))
 	}
 
-	fn update(&mut self, prop: XUpdateProp) -> (WindowProp, &str) {
+	fn update(&mut self, prop: XUpdateProp) {
 		match prop {
-			XUpdateProp::Title(value) => { self.title = value; (WindowProp::Title, &self.title) },
-			XUpdateProp::State(value) => { self.state = value; (WindowProp::State, self.state.as_ref()) },
-			XUpdateProp::Display(value) => { self.display = value; (WindowProp::Display, &self.display) },
+			XUpdateProp::Title(value) => self.title = value,
+			XUpdateProp::State(value, states) => { self.state = value; self.states = states; },
+			XUpdateProp::Decorated(value) => self.decorated = value,
+			XUpdateProp::Display(name, index) => { self.display = name; self.display_index = index; },
+			XUpdateProp::Class(name, class) => { self.name = name; self.class = class; },
+			XUpdateProp::Type(value) => self.r#type = value,
+			XUpdateProp::Role(value) => self.role = value,
+			XUpdateProp::Stack(value) => self.stack = value,
+			XUpdateProp::UserTime(value) => self.user_time = value,
+			XUpdateProp::Workspace(value) => self.workspace = value,
+			XUpdateProp::Geometry(x, y, width, height) => { self.x = x; self.y = y; self.width = width; self.height = height; },
+		}
+	}
+
+	fn matches(&self, prop: &XUpdateProp) -> bool {
+		match prop {
+			XUpdateProp::Title(value) => self.title == *value,
+			XUpdateProp::State(value, states) => self.state == *value && self.states == *states,
+			XUpdateProp::Decorated(value) => self.decorated == *value,
+			XUpdateProp::Display(name, index) => self.display == *name && self.display_index == *index,
+			XUpdateProp::Class(name, class) => self.name == *name && self.class == *class,
+			XUpdateProp::Type(value) => self.r#type == *value,
+			XUpdateProp::Role(value) => self.role == *value,
+			XUpdateProp::Stack(value) => self.stack == *value,
+			XUpdateProp::UserTime(value) => self.user_time == *value,
+			XUpdateProp::Workspace(value) => self.workspace == *value,
+			XUpdateProp::Geometry(x, y, width, height) => self.x == *x && self.y == *y && self.width == *width && self.height == *height,
 		}
 	}
 }
@@ -584,25 +1611,83 @@ impl Default for XWindow {
 		Self {
 			id: 0,
 			top_id: 0,
+			root: 0,
 			name: Default::default(),
 			class: Default::default(),
+			icon: Default::default(),
 			pid: 0,
+			comm: Default::default(),
 			title: Default::default(),
 			r#type: WindowType::None,
 			role: Default::default(),
+			leader: 0,
 			state: WindowState::None,
+			states: Default::default(),
+			decorated: true,
+			accepts_focus: true,
 			display: Default::default(),
+			display_index: 0,
+			x: 0,
+			y: 0,
+			width: 0,
+			height: 0,
+			workspace: Default::default(),
+			stack: -1,
+			user_time: 0,
+			depth: 0,
+			visual: 0,
+			user_time_id: 0,
 		}
 	}
 }
 
 #[derive(Debug)]
 struct XDisplay {
+	/// Root window of the screen the display belongs to
+	root: Window,
 	name: Box<str>,
 	x: i16,
 	y: i16,
 	w: i16,
 	h: i16,
+	primary: bool,
+	width_mm: u32,
+	refresh_rate: f64,
+}
+
+/// Indexes of the displays of each screen sorted by their left edge, so finding the display of a window
+/// (e.g. while it's being dragged) only tests the displays it could horizontally overlap
+#[derive(Debug, Default)]
+struct DisplayIndex {
+	by_root: HashMap<Window, Vec<usize>>,
+}
+
+impl DisplayIndex {
+	fn new(displays: &[XDisplay]) -> Self {
+		let mut by_root: HashMap<Window, Vec<usize>> = HashMap::new();
+
+		for (i, d) in displays.iter().enumerate() {
+			by_root.entry(d.root).or_default().push(i);
+		}
+
+		for indexes in by_root.values_mut() {
+			indexes.sort_by_key(|&i| displays[i].x);
+		}
+
+		Self { by_root }
+	}
+
+	/// Get the indexes of the displays of a screen that overlap the horizontal span `x1..x2`, ordered by their left edge
+	fn candidates<'a>(&'a self, displays: &'a [XDisplay], root: Window, x1: i32, x2: i32) -> impl Iterator<Item = usize> + 'a {
+		let indexes = self.by_root.get(&root).map(Vec::as_slice).unwrap_or_default();
+
+		// displays starting at or after the right edge can't overlap
+		let end = indexes.partition_point(|&i| (displays[i].x as i32) < x2);
+
+		indexes[..end].iter()
+			.copied()
+			.filter(move |&i| displays[i].x as i32 + displays[i].w as i32 > x1)
+	}
 }
 
 type PartialMatch = (Window, Box<str>, Box<str>);
@@ -612,12 +1697,28 @@ type PartialMatch = (Window, Box<str>, Box<str>);
 struct Atoms {
 	UTF8_STRING: Atom,
 	ACTIVE_WINDOW: Atom,
+	GTK_APPLICATION_ID: Atom,
+	GTK_FRAME_EXTENTS: Atom,
+	KDE_DESKTOP_FILE: Atom,
+	MOTIF_WM_HINTS: Atom,
+	CLIENT_LIST_STACKING: Atom,
+	DESKTOP_NAMES: Atom,
+	RESTACK_WINDOW: Atom,
+	SUPPORTED: Atom,
+	WM_CLIENT_LEADER: Atom,
+	WM_DESKTOP: Atom,
 	WM_NAME: Atom,
+	WM_VISIBLE_NAME: Atom,
 	WM_PID: Atom,
+	WM_PROTOCOLS: Atom,
 	WM_STATE: Atom,
 	WM_STATE_MAXIMIZED_HORZ: Atom,
 	WM_STATE_MAXIMIZED_VERT: Atom,
 	WM_STATE_FULLSCREEN: Atom,
+	WM_STATE_SHADED: Atom,
+	WM_TAKE_FOCUS: Atom,
+	WM_USER_TIME: Atom,
+	WM_USER_TIME_WINDOW: Atom,
 	WM_WINDOW_ROLE: Atom,
 	WM_WINDOW_TYPE: Atom,
 }
@@ -632,12 +1733,28 @@ impl Atoms {
 		concurrent!(
 			let UTF8_STRING             = Self::get_atom(&conn, b"UTF8_STRING"),
 			let ACTIVE_WINDOW           = Self::get_atom(&conn, b"_NET_ACTIVE_WINDOW"),
+			let GTK_APPLICATION_ID      = Self::get_atom(&conn, b"_GTK_APPLICATION_ID"),
+			let GTK_FRAME_EXTENTS       = Self::get_atom(&conn, b"_GTK_FRAME_EXTENTS"),
+			let KDE_DESKTOP_FILE        = Self::get_atom(&conn, b"_KDE_NET_WM_DESKTOP_FILE"),
+			let MOTIF_WM_HINTS          = Self::get_atom(&conn, b"_MOTIF_WM_HINTS"),
+			let CLIENT_LIST_STACKING    = Self::get_atom(&conn, b"_NET_CLIENT_LIST_STACKING"),
+			let DESKTOP_NAMES           = Self::get_atom(&conn, b"_NET_DESKTOP_NAMES"),
+			let RESTACK_WINDOW          = Self::get_atom(&conn, b"_NET_RESTACK_WINDOW"),
+			let SUPPORTED               = Self::get_atom(&conn, b"_NET_SUPPORTED"),
+			let WM_CLIENT_LEADER        = Self::get_atom(&conn, b"WM_CLIENT_LEADER"),
+			let WM_DESKTOP              = Self::get_atom(&conn, b"_NET_WM_DESKTOP"),
 			let WM_NAME                 = Self::get_atom(&conn, b"_NET_WM_NAME"),
+			let WM_VISIBLE_NAME         = Self::get_atom(&conn, b"_NET_WM_VISIBLE_NAME"),
 			let WM_PID                  = Self::get_atom(&conn, b"_NET_WM_PID"),
+			let WM_PROTOCOLS            = Self::get_atom(&conn, b"WM_PROTOCOLS"),
 			let WM_STATE                = Self::get_atom(&conn, b"_NET_WM_STATE"),
 			let WM_STATE_MAXIMIZED_HORZ = Self::get_atom(&conn, b"_NET_WM_STATE_MAXIMIZED_HORZ"),
 			let WM_STATE_MAXIMIZED_VERT = Self::get_atom(&conn, b"_NET_WM_STATE_MAXIMIZED_VERT"),
 			let WM_STATE_FULLSCREEN     = Self::get_atom(&conn, b"_NET_WM_STATE_FULLSCREEN"),
+			let WM_STATE_SHADED         = Self::get_atom(&conn, b"_NET_WM_STATE_SHADED"),
+			let WM_TAKE_FOCUS           = Self::get_atom(&conn, b"WM_TAKE_FOCUS"),
+			let WM_USER_TIME            = Self::get_atom(&conn, b"_NET_WM_USER_TIME"),
+			let WM_USER_TIME_WINDOW     = Self::get_atom(&conn, b"_NET_WM_USER_TIME_WINDOW"),
 			let WM_WINDOW_ROLE          = Self::get_atom(&conn, b"WM_WINDOW_ROLE"),
 			let WM_WINDOW_TYPE          = Self::get_atom(&conn, b"_NET_WM_WINDOW_TYPE"),
 		);
@@ -645,17 +1762,51 @@ impl Atoms {
 		Ok(Self {
 			UTF8_STRING: UTF8_STRING?,
 			ACTIVE_WINDOW: ACTIVE_WINDOW?,
+			GTK_APPLICATION_ID: GTK_APPLICATION_ID?,
+			GTK_FRAME_EXTENTS: GTK_FRAME_EXTENTS?,
+			KDE_DESKTOP_FILE: KDE_DESKTOP_FILE?,
+			MOTIF_WM_HINTS: MOTIF_WM_HINTS?,
+			CLIENT_LIST_STACKING: CLIENT_LIST_STACKING?,
+			DESKTOP_NAMES: DESKTOP_NAMES?,
+			RESTACK_WINDOW: RESTACK_WINDOW?,
+			SUPPORTED: SUPPORTED?,
+			WM_CLIENT_LEADER: WM_CLIENT_LEADER?,
+			WM_DESKTOP: WM_DESKTOP?,
 			WM_NAME: WM_NAME?,
+			WM_VISIBLE_NAME: WM_VISIBLE_NAME?,
 			WM_PID: WM_PID?,
+			WM_PROTOCOLS: WM_PROTOCOLS?,
 			WM_STATE: WM_STATE?,
 			WM_STATE_MAXIMIZED_HORZ: WM_STATE_MAXIMIZED_HORZ?,
 			WM_STATE_MAXIMIZED_VERT: WM_STATE_MAXIMIZED_VERT?,
 			WM_STATE_FULLSCREEN: WM_STATE_FULLSCREEN?,
+			WM_STATE_SHADED: WM_STATE_SHADED?,
+			WM_TAKE_FOCUS: WM_TAKE_FOCUS?,
+			WM_USER_TIME: WM_USER_TIME?,
+			WM_USER_TIME_WINDOW: WM_USER_TIME_WINDOW?,
 			WM_WINDOW_ROLE: WM_WINDOW_ROLE?,
 			WM_WINDOW_TYPE: WM_WINDOW_TYPE?,
 		})
 	}
 
+	/// Load the `_NET_WM_STATE_*` atoms reported in the `states` property, mapped to their short names
+	async fn load_window_states(conn: &RustConnection) -> Result<HashMap<Atom, &'static str>> {
+		let mut cookies = Vec::new();
+
+		// send every request before waiting for the replies
+		for name in WINDOW_STATES {
+			cookies.push((name, conn.intern_atom(false, format!("_NET_WM_STATE_{name}").as_bytes()).await?));
+		}
+
+		let mut states = HashMap::new();
+
+		for (name, cookie) in cookies {
+			states.insert(cookie.reply().await?.atom, name);
+		}
+
+		Ok(states)
+	}
+
 	#[allow(non_snake_case)]
 	async fn load_window_types(conn: &RustConnection) -> Result<HashMap<u32, WindowType>> {
 		concurrent!(
@@ -693,3 +1844,186 @@ impl Atoms {
 		]))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn display(root: Window, x: i16, w: i16) -> XDisplay {
+		XDisplay {
+			root,
+			name: Default::default(),
+			x,
+			y: 0,
+			w,
+			h: 1080,
+			primary: false,
+			width_mm: 0,
+			refresh_rate: 0.0,
+		}
+	}
+
+	/// The displays a full scan would test, in display order
+	fn scan(displays: &[XDisplay], root: Window, x1: i32, x2: i32) -> Vec<usize> {
+		(0..displays.len())
+			.filter(|&i| displays[i].root == root)
+			.filter(|&i| (displays[i].x as i32) < x2 && displays[i].x as i32 + displays[i].w as i32 > x1)
+			.collect()
+	}
+
+	#[test]
+	fn pointer_enter_root() {
+		let roots = [0x1d6];
+		let window = XWindow { id: 0x400003, top_id: 0x400001, root: 0x1d6, ..Default::default() };
+
+		// leaving a window for the desktop clears it
+		assert_eq!(pointer_enter(&roots, &window, 0x1d6, 0, NotifyMode::NORMAL), PointerEnter::Clear);
+		// but not when a grab crosses the root
+		assert_eq!(pointer_enter(&roots, &window, 0x1d6, 0, NotifyMode::GRAB), PointerEnter::Ignore);
+		assert_eq!(pointer_enter(&roots, &window, 0x1d6, 0, NotifyMode::UNGRAB), PointerEnter::Ignore);
+		// or when it's already clear
+		assert_eq!(pointer_enter(&roots, &XWindow::default(), 0x1d6, 0, NotifyMode::NORMAL), PointerEnter::Ignore);
+	}
+
+	#[test]
+	fn pointer_enter_window() {
+		let roots = [0x1d6];
+		let window = XWindow { id: 0x400003, top_id: 0x400001, root: 0x1d6, ..Default::default() };
+
+		// entering the same window again, through its frame or from the root
+		assert_eq!(pointer_enter(&roots, &window, 0x400001, 0, NotifyMode::NORMAL), PointerEnter::Ignore);
+		assert_eq!(pointer_enter(&roots, &window, 0x400003, 0, NotifyMode::NORMAL), PointerEnter::Ignore);
+		assert_eq!(pointer_enter(&roots, &window, 0x1d6, 0x400003, NotifyMode::NORMAL), PointerEnter::Ignore);
+		// entering another window, including from the desktop
+		assert_eq!(pointer_enter(&roots, &window, 0x600001, 0, NotifyMode::NORMAL), PointerEnter::Resolve);
+		assert_eq!(pointer_enter(&roots, &XWindow::default(), 0x600001, 0, NotifyMode::NORMAL), PointerEnter::Resolve);
+	}
+
+	#[test]
+	fn move_across_displays() {
+		let geometry = |x: i32| XUpdateProp::Geometry(x, 0, 800, 600);
+		let display = |x: i32| if x < 1920 { XUpdateProp::Display("DP-1".into(), 0) } else { XUpdateProp::Display("DP-2".into(), 1) };
+
+		let mut window = XWindow::default();
+		window.update(geometry(100));
+		window.update(display(100));
+
+		let mut displays = Vec::new();
+
+		let mut apply = |window: &mut XWindow, props: Option<Vec<XUpdateProp>>| {
+			for prop in props.into_iter().flatten() {
+				if matches!(prop, XUpdateProp::Display(..)) && !window.matches(&prop) {
+					displays.push(prop.clone());
+				}
+
+				window.update(prop);
+			}
+		};
+
+		// a burst of moves crossing onto the second display, with the leading debounced move handled after the crossing
+		let (props, debounce) = move_update(&window, geometry(1000), display(1000), true);
+		assert!(props.is_none() && debounce);
+
+		let (props, debounce) = move_update(&window, geometry(2000), display(2000), true);
+		assert!(props.is_some() && debounce);
+		apply(&mut window, props);
+
+		let props = debounced_move_update(&window, geometry(1000));
+		apply(&mut window, props);
+
+		let (props, debounce) = move_update(&window, geometry(2100), display(2100), true);
+		assert!(props.is_none() && debounce);
+
+		let props = debounced_move_update(&window, geometry(2100));
+		apply(&mut window, props);
+
+		assert_eq!(displays.len(), 1);
+		assert!(window.matches(&display(2100)) && window.matches(&geometry(2100)));
+	}
+
+	#[test]
+	fn move_without_debounce() {
+		let mut window = XWindow::default();
+		window.update(XUpdateProp::Geometry(100, 0, 800, 600));
+
+		let (props, debounce) = move_update(&window, XUpdateProp::Geometry(200, 0, 800, 600), XUpdateProp::Display(Default::default(), 0), false);
+		assert_eq!(props.map(|props| props.len()), Some(2));
+		assert!(!debounce);
+
+		// unchanged
+		let (props, debounce) = move_update(&window, XUpdateProp::Geometry(100, 0, 800, 600), XUpdateProp::Display(Default::default(), 0), false);
+		assert!(props.is_none() && !debounce);
+	}
+
+	#[test]
+	fn select_new_windows_only() {
+		let window = XWindow { id: 5, top_id: 4, user_time_id: 6, ..Default::default() };
+		let other = XWindow { id: 8, top_id: 7, user_time_id: 9, ..Default::default() };
+		let none = XWindow::default();
+
+		assert_eq!(windows_to_select(&window, &none, &none), (Some(5), Some(6)));
+		assert_eq!(windows_to_select(&window, &other, &none), (Some(5), Some(6)));
+
+		// already stored
+		assert_eq!(windows_to_select(&window, &window, &none), (None, None));
+		assert_eq!(windows_to_select(&window, &other, &window), (None, None));
+
+		// another window of the same application sharing its user time window
+		let sibling = XWindow { id: 10, top_id: 11, user_time_id: 6, ..Default::default() };
+		assert_eq!(windows_to_select(&sibling, &window, &none), (Some(10), None));
+	}
+
+	#[test]
+	fn select_skips_frames_and_own_user_time() {
+		let none = XWindow::default();
+
+		// no window, or a window without a separate frame or user time window
+		assert_eq!(windows_to_select(&none, &none, &none), (None, None));
+		assert_eq!(windows_to_select(&XWindow { id: 5, top_id: 5, user_time_id: 5, ..Default::default() }, &none, &none), (None, None));
+	}
+
+	#[test]
+	fn wm_class_two_components() {
+		assert_eq!(parse_wm_class(b"Navigator\0Firefox\0"), ("navigator".to_string(), "firefox".to_string()));
+		assert_eq!(parse_wm_class(b"sun-awt-X11-XFramePeer\0Jetbrains Toolbox\0"), ("sun-awt-x11-xframepeer".to_string(), "jetbrains-toolbox".to_string()));
+	}
+
+	#[test]
+	fn wm_class_one_component() {
+		assert_eq!(parse_wm_class(b"Steam\0"), ("steam".to_string(), "steam".to_string()));
+		// unterminated
+		assert_eq!(parse_wm_class(b"Steam"), ("steam".to_string(), "steam".to_string()));
+	}
+
+	#[test]
+	fn wm_class_empty() {
+		assert_eq!(parse_wm_class(b""), (String::new(), String::new()));
+		assert_eq!(parse_wm_class(b"\0"), (String::new(), String::new()));
+		assert_eq!(parse_wm_class(b"\0\0"), (String::new(), String::new()));
+	}
+
+	#[test]
+	fn display_candidates_match_scan() {
+		let layouts = [
+			// side by side, out of order
+			vec![display(1, 1920, 1920), display(1, 0, 1920), display(1, 3840, 1280)],
+			// mirrored and partially overlapping
+			vec![display(1, 0, 1920), display(1, 0, 1920), display(1, 960, 1920), display(1, -1280, 1400)],
+			// nested, and on several screens
+			vec![display(1, 0, 3840), display(2, 0, 1920), display(1, 1000, 800), display(2, 1920, 1920), display(1, 1000, 800)],
+		];
+
+		for displays in &layouts {
+			let index = DisplayIndex::new(displays);
+
+			for root in [1, 2, 3] {
+				for (x1, x2) in [(-2000, -1500), (-100, 10), (0, 1), (959, 961), (1000, 1800), (1919, 1921), (100, 5000), (5000, 6000)] {
+					let mut candidates: Vec<_> = index.candidates(displays, root, x1, x2).collect();
+					candidates.sort_unstable();
+
+					assert_eq!(candidates, scan(displays, root, x1, x2), "root {root}, span {x1}..{x2}, displays {displays:?}");
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,64 @@
+use crate::types::*;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// A window change received by the service, as written to a recording (one JSON object per line)
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Record {
+	/// Milliseconds since the recording started
+	pub time: u64,
+	#[serde(flatten)]
+	pub call: Call,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "call", rename_all = "snake_case")]
+pub enum Call {
+	SetWindow {
+		context: WindowContext,
+		window: WindowDict,
+	},
+	UpdateWindow {
+		context: WindowContext,
+		key: WindowProp,
+		value: String,
+	},
+	UpdateWindowProps {
+		context: WindowContext,
+		props: Vec<(WindowProp, String)>,
+	},
+}
+
+/// Writes window changes to a JSONL file, to be fed back later by the replay provider
+pub struct Recorder {
+	writer: BufWriter<File>,
+	start: Instant,
+}
+
+impl Recorder {
+	pub fn create(path: &Path) -> Result<Self> {
+		Ok(Self {
+			writer: BufWriter::new(File::create(path)?),
+			start: Instant::now(),
+		})
+	}
+
+	pub fn record(&mut self, call: Call) -> Result<()> {
+		let record = Record {
+			time: self.start.elapsed().as_millis() as u64,
+			call,
+		};
+
+		serde_json::to_writer(&mut self.writer, &record)?;
+		self.writer.write_all(b"\n")?;
+
+		// flush every record so the file is complete even if the daemon is killed
+		self.writer.flush()?;
+
+		Ok(())
+	}
+}
@@ -1,13 +1,19 @@
 use crate::types::*;
+use super::Args;
+use super::providers::{detect as detect_provider, desktop_icon, process_comm, WindowProvider};
+use super::record::{Call, Recorder};
+use std::collections::HashMap;
 use std::future;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use anyhow::{anyhow, Result};
 use colored::Colorize;
 use tokio::sync::oneshot::Sender;
-use zbus::{connection, interface, fdo, object_server::SignalEmitter};
+use zbus::{connection, interface, fdo, message::Header, names::WellKnownName, object_server::SignalEmitter, Connection};
 
 pub struct ServiceProxy<'a> {
 	pub application: ApplicationProxy<'a>,
 	pub windows: WindowsProxy<'a>,
+	pub displays: DisplaysProxy<'a>,
 }
 
 struct Application {
@@ -40,6 +46,26 @@ impl Application {
 struct Windows {
 	active_window: WindowDict,
 	pointer_window: WindowDict,
+	/// When the current windows became active and under the pointer, for their `age`
+	active_since: Instant,
+	pointer_since: Instant,
+	/// When either window last changed, in milliseconds since the Unix epoch
+	last_changed: u64,
+	/// How many times the active window changed to a different window
+	focus_changes: u64,
+	allow_override: bool,
+	/// Bus name of the compositor running the provider, if it sets windows from outside the daemon
+	provider_host: Option<&'static str>,
+	/// Unique name of the last known owner of `provider_host`
+	provider_owner: Option<String>,
+	active_only: bool,
+	/// Keep the last active window when focus moves to no window
+	sticky_active: bool,
+	initialized: (bool, bool),
+	ready: Option<Sender<()>>,
+	recorder: Option<Recorder>,
+	/// Icon names resolved from desktop entries, by the ids they were resolved from
+	icons: HashMap<[String; 3], String>,
 }
 
 #[interface(
@@ -52,73 +78,434 @@ struct Windows {
 impl Windows {
 	#[zbus(property)]
 	async fn active_window(&self) -> DictMap {
-		self.active_window.as_map()
+		with_age(&self.active_window, self.active_since)
 	}
 
 	#[zbus(property)]
 	async fn pointer_window(&self) -> DictMap {
-		self.pointer_window.as_map()
+		with_age(&self.pointer_window, self.pointer_since)
 	}
 
+	/// Whether the pointer window is tracked, which is disabled by `--active-only`
+	#[zbus(property)]
+	async fn tracks_pointer(&self) -> bool {
+		!self.active_only
+	}
+
+	/// When either window last changed, in milliseconds since the Unix epoch (0 if never)
+	#[zbus(property)]
+	async fn last_changed(&self) -> u64 {
+		self.last_changed
+	}
+
+	/// How many times the active window changed to a different window since the daemon started or the count was reset
+	#[zbus(property)]
+	async fn focus_changes(&self) -> u64 {
+		self.focus_changes
+	}
+
+	async fn reset_focus_changes(
+		&mut self,
+		#[zbus(signal_emitter)]
+		emitter: SignalEmitter<'_>
+	) -> fdo::Result<()> {
+		self.focus_changes = 0;
+		self.focus_changes_changed(&emitter).await?;
+
+		Ok(())
+	}
+
+	/// Get only the given properties of the active or pointer window, for clients that poll a few of them
+	async fn get_properties(&self, context: WindowContext, props: Vec<WindowProp>) -> fdo::Result<DictMap> {
+		let mut window = match context {
+			WindowContext::Active => with_age(&self.active_window, self.active_since),
+			WindowContext::Pointer => with_age(&self.pointer_window, self.pointer_since),
+			WindowContext::Both => return Err(fdo::Error::InvalidArgs("Expected the `active` or `pointer` context".into())),
+		};
+
+		window.retain(|key, _| props.iter().any(|prop| prop.to_string() == *key));
+
+		Ok(window)
+	}
+
+	/// Emitted when the title of the active or pointer window changes, for clients only watching the title
+	#[zbus(signal)]
+	async fn title_changed(emitter: &SignalEmitter<'_>, context: WindowContext, title: &str) -> zbus::Result<()>;
+
+	/// Emitted when the state of the active or pointer window changes, for clients only watching the state
+	#[zbus(signal)]
+	async fn state_changed(emitter: &SignalEmitter<'_>, context: WindowContext, state: &str) -> zbus::Result<()>;
+
 	async fn set_window(
 		&mut self,
 		context: WindowContext,
 		window: DictMap<'_>,
+		#[zbus(header)]
+		header: Header<'_>,
+		#[zbus(connection)]
+		connection: &Connection,
+		#[zbus(signal_emitter)]
+		emitter: SignalEmitter<'_>
+	) -> fdo::Result<()> {
+		self.check_provider(&header, connection).await?;
+		self.replace_window(context, window, &emitter).await
+	}
+
+	async fn override_window(
+		&mut self,
+		context: WindowContext,
+		window: DictMap<'_>,
+		#[zbus(signal_emitter)]
+		emitter: SignalEmitter<'_>
+	) -> fdo::Result<()> {
+		if !self.allow_override {
+			return Err(fdo::Error::AccessDenied("Overriding windows is disabled. Start the daemon with `--allow-override` to enable it.".into()));
+		}
+
+		self.replace_window(context, window, &emitter).await
+	}
+
+	async fn update_window(
+		&mut self,
+		context: WindowContext,
+		key: WindowProp,
+		value: &str,
+		#[zbus(header)]
+		header: Header<'_>,
+		#[zbus(connection)]
+		connection: &Connection,
 		#[zbus(signal_emitter)]
 		emitter: SignalEmitter<'_>
 	) -> fdo::Result<()> {
-		let dict = WindowDict::try_from(window)?;
+		self.check_provider(&header, connection).await?;
+
+		let Some(context) = self.apply_updates(context, &[(key, value.into())], &emitter).await? else {
+			return Ok(());
+		};
+
+		tracing::debug!(?context, %key, value, "Window updated");
+
+		self.record(|| Call::UpdateWindow { context, key, value: value.into() });
+
+		Ok(())
+	}
+
+	/// Update several properties of a window at once, for properties that change together (e.g. the geometry and display)
+	async fn update_window_props(
+		&mut self,
+		context: WindowContext,
+		props: Vec<(WindowProp, String)>,
+		#[zbus(header)]
+		header: Header<'_>,
+		#[zbus(connection)]
+		connection: &Connection,
+		#[zbus(signal_emitter)]
+		emitter: SignalEmitter<'_>
+	) -> fdo::Result<()> {
+		self.check_provider(&header, connection).await?;
+
+		let Some(context) = self.apply_updates(context, &props, &emitter).await? else {
+			return Ok(());
+		};
+
+		tracing::debug!(?context, ?props, "Window updated");
+
+		self.record(|| Call::UpdateWindowProps { context, props });
+
+		Ok(())
+	}
+}
+
+impl Windows {
+	/// Only accept window changes from the provider, which runs in the daemon itself or in the compositor hosting its script,
+	/// so clients can't bypass `--allow-override`
+	async fn check_provider(&mut self, header: &Header<'_>, connection: &Connection) -> fdo::Result<()> {
+		let denied = || fdo::Error::AccessDenied("Only the window provider can set windows. Use `wctx set` with a daemon started with `--allow-override` instead.".into());
+
+		let Some(sender) = header.sender().map(|sender| sender.as_str()) else {
+			return Err(denied());
+		};
+
+		if connection.unique_name().is_some_and(|name| name.as_str() == sender) {
+			return Ok(());
+		}
+
+		let Some(host) = self.provider_host else {
+			return Err(denied());
+		};
+
+		// the compositor gets a new unique name when it restarts, so look it up again on a mismatch
+		if self.provider_owner.as_deref() != Some(sender) {
+			let owner = fdo::DBusProxy::new(connection).await?
+				.get_name_owner(WellKnownName::from_static_str_unchecked(host).into()).await;
+
+			self.provider_owner = owner.ok().map(|owner| owner.to_string());
+		}
+
+		if self.provider_owner.as_deref() == Some(sender) {
+			Ok(())
+		} else {
+			Err(denied())
+		}
+	}
+
+	async fn replace_window(&mut self, context: WindowContext, window: DictMap<'_>, emitter: &SignalEmitter<'_>) -> fdo::Result<()> {
+		let mut context = match context {
+			// providers that can't skip pointer tracking (e.g. KWin scripts) still send it
+			WindowContext::Pointer if self.active_only => return Ok(()),
+			WindowContext::Both if self.active_only => WindowContext::Active,
+			context => context,
+		};
+
+		// providers that can't read process info (e.g. KWin scripts) only send the pid
+		let has_comm = window.contains_key("comm");
+		let mut dict = WindowDict::try_from(window)?;
+
+		// focus moved to no window (e.g. the desktop), so keep the last active window instead of clearing it
+		if self.sticky_active && dict.id.is_empty() && context != WindowContext::Pointer {
+			self.mark_initialized(WindowContext::Active);
+
+			if context == WindowContext::Active {
+				return Ok(());
+			}
+
+			context = WindowContext::Pointer;
+		}
+
+		if !has_comm {
+			dict.comm = process_comm(dict.pid).unwrap_or_default();
+		}
+
+		// the icon from the provider is only a hint of the desktop entry (e.g. the Wayland app id)
+		dict.icon = self.icon(&dict);
+
+		tracing::debug!(?context, window_id = %dict.id, class = %dict.class, title = %dict.title, "Window changed");
+
+		self.record(|| Call::SetWindow { context, window: dict.clone() });
+
+		// only a different window resets the age, not updated properties of the same one
+		let now = Instant::now();
+
+		let focus_changed = context != WindowContext::Pointer && dict.id != self.active_window.id;
+
+		if focus_changed {
+			self.active_since = now;
+			self.focus_changes += 1;
+		}
+
+		if context != WindowContext::Active && dict.id != self.pointer_window.id {
+			self.pointer_since = now;
+		}
+
+		let previous = self.signal_props();
 
 		match context {
 			WindowContext::Both => {
 				self.active_window = dict.clone();
 				self.pointer_window = dict;
-				self.active_window_changed(&emitter).await?;
-				self.pointer_window_changed(&emitter).await?;
+				self.active_window_changed(emitter).await?;
+				self.pointer_window_changed(emitter).await?;
 			}
 			WindowContext::Active => {
 				self.active_window = dict;
-				self.active_window_changed(&emitter).await?;
+				self.active_window_changed(emitter).await?;
 			}
 			WindowContext::Pointer => {
 				self.pointer_window = dict;
-				self.pointer_window_changed(&emitter).await?;
+				self.pointer_window_changed(emitter).await?;
 			}
 		};
 
+		if focus_changed {
+			self.focus_changes_changed(emitter).await?;
+		}
+
+		self.emit_prop_changes(emitter, previous).await?;
+		self.touch(emitter).await?;
+		self.mark_initialized(context);
+
 		Ok(())
 	}
 
-	async fn update_window(
-		&mut self,
-		context: WindowContext,
-		key: WindowProp,
-		value: &str,
-		#[zbus(signal_emitter)]
-		emitter: SignalEmitter<'_>
-	) -> fdo::Result<()> {
+	/// Apply property updates to the windows in the context, returning the context they were applied to (if any)
+	async fn apply_updates(&mut self, context: WindowContext, props: &[(WindowProp, String)], emitter: &SignalEmitter<'_>) -> fdo::Result<Option<WindowContext>> {
+		let context = match context {
+			WindowContext::Pointer if self.active_only => return Ok(None),
+			WindowContext::Both if self.active_only => WindowContext::Active,
+			context => context,
+		};
+
+		let new_id = props.iter().rev().find(|(key, _)| *key == WindowProp::ID).map(|(_, value)| value.as_str());
+		let focus_changed = new_id.is_some_and(|id| context != WindowContext::Pointer && id != self.active_window.id);
+
+		if let Some(id) = new_id {
+			let now = Instant::now();
+
+			if focus_changed {
+				self.active_since = now;
+				self.focus_changes += 1;
+			}
+
+			if context != WindowContext::Active && id != self.pointer_window.id {
+				self.pointer_since = now;
+			}
+		}
+
+		let previous = self.signal_props();
+
+		for (key, value) in props {
+			if context != WindowContext::Pointer {
+				self.active_window.update(*key, value)?;
+			}
+
+			if context != WindowContext::Active {
+				self.pointer_window.update(*key, value)?;
+			}
+		}
+
+		if context != WindowContext::Pointer {
+			self.active_window_changed(emitter).await?;
+		}
+
+		if context != WindowContext::Active {
+			self.pointer_window_changed(emitter).await?;
+		}
+
+		if focus_changed {
+			self.focus_changes_changed(emitter).await?;
+		}
+
+		self.emit_prop_changes(emitter, previous).await?;
+		self.touch(emitter).await?;
+
+		Ok(Some(context))
+	}
+
+	/// Signal readiness once the provider has set both the active and pointer windows
+	fn mark_initialized(&mut self, context: WindowContext) {
 		match context {
-			WindowContext::Both => {
-				self.active_window.update(key, value)?;
-				self.pointer_window.update(key, value)?;
-				self.active_window_changed(&emitter).await?;
-				self.pointer_window_changed(&emitter).await?;
+			WindowContext::Both => self.initialized = (true, true),
+			WindowContext::Active => self.initialized.0 = true,
+			WindowContext::Pointer => self.initialized.1 = true,
+		}
+
+		if self.initialized == (true, true) {
+			if let Some(ready) = self.ready.take() {
+				let _ = ready.send(());
 			}
-			WindowContext::Active => {
-				self.active_window.update(key, value)?;
-				self.active_window_changed(&emitter).await?;
+		}
+	}
+
+	/// The properties of the active and pointer windows that have their own signals
+	fn signal_props(&self) -> [(String, WindowState); 2] {
+		[&self.active_window, &self.pointer_window].map(|window| (window.title.clone(), window.state))
+	}
+
+	/// Emit the per-property signals for the properties that changed since `previous`
+	async fn emit_prop_changes(&self, emitter: &SignalEmitter<'_>, previous: [(String, WindowState); 2]) -> zbus::Result<()> {
+		let contexts = [WindowContext::Active, WindowContext::Pointer];
+
+		for ((context, (title, state)), window) in contexts.into_iter().zip(previous).zip([&self.active_window, &self.pointer_window]) {
+			if title != window.title {
+				Self::title_changed(emitter, context, &window.title).await?;
 			}
-			WindowContext::Pointer => {
-				self.pointer_window.update(key, value)?;
-				self.pointer_window_changed(&emitter).await?;
+
+			if state != window.state {
+				Self::state_changed(emitter, context, window.state.as_ref()).await?;
 			}
+		}
+
+		Ok(())
+	}
+
+	async fn touch(&mut self, emitter: &SignalEmitter<'_>) -> zbus::Result<()> {
+		self.last_changed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+		self.last_changed_changed(emitter).await
+	}
+
+	fn icon(&mut self, window: &WindowDict) -> String {
+		let ids = [window.icon.clone(), window.class.clone(), window.name.clone()];
+
+		self.icons.entry(ids)
+			.or_insert_with_key(|ids| desktop_icon(&ids.each_ref().map(String::as_str)))
+			.clone()
+	}
+
+	fn record(&mut self, call: impl FnOnce() -> Call) {
+		let Some(recorder) = &mut self.recorder else {
+			return;
 		};
 
+		if let Err(err) = recorder.record(call()) {
+			tracing::warn!(error = %err, "Failed to record window change");
+			eprintln!("{} {}", "Failed to record window change:".bright_red(), err);
+		}
+	}
+}
+
+/// Get a window with its age computed at the time of the query
+fn with_age(window: &WindowDict, since: Instant) -> DictMap<'static> {
+	let mut window = window.clone();
+	window.age = since.elapsed().as_secs() as u32;
+	window.into()
+}
+
+struct Displays {
+	displays: Vec<DisplayDict>,
+}
+
+#[interface(
+	name = "org.wctx.Displays",
+	proxy(
+		default_path = "/",
+		default_service = "org.wctx",
+	),
+)]
+impl Displays {
+	async fn list_displays(&self) -> Vec<DictMap> {
+		self.displays.iter().cloned().map(Into::into).collect()
+	}
+
+	async fn set_displays(&mut self, displays: Vec<DictMap<'_>>) -> fdo::Result<()> {
+		self.displays = displays.into_iter()
+			.map(DisplayDict::try_from)
+			.collect::<fdo::Result<_>>()?;
+
 		Ok(())
 	}
 }
 
-pub async fn serve(tx: Sender<ServiceProxy<'_>>) -> Result<()> {
+/// Options of the D-Bus service, taken from the daemon arguments
+pub struct ServiceOptions {
+	/// Whether the provider sets the pointer window, so readiness waits for it
+	tracks_pointer: bool,
+	allow_override: bool,
+	/// Bus name of the compositor running the provider, see `WindowProvider::host_bus_name`
+	provider_host: Option<&'static str>,
+	active_only: bool,
+	sticky_active: bool,
+	system_bus: bool,
+	recorder: Option<Recorder>,
+}
+
+impl ServiceOptions {
+	pub fn new(args: &Args, recorder: Option<Recorder>) -> Self {
+		let provider = args.provider.or_else(detect_provider);
+
+		Self {
+			// the pointer window is never set when the provider doesn't track it, or with `--active-only`
+			tracks_pointer: !args.active_only && provider.is_some_and(WindowProvider::tracks_pointer),
+			allow_override: args.allow_override,
+			provider_host: provider.and_then(WindowProvider::host_bus_name),
+			active_only: args.active_only,
+			sticky_active: args.sticky_active,
+			system_bus: args.system_bus,
+			recorder,
+		}
+	}
+}
+
+pub async fn serve(tx: Sender<ServiceProxy<'_>>, ready: Sender<()>, options: ServiceOptions) -> Result<()> {
 	let application = Application {
 		status: Default::default(),
 	};
@@ -126,12 +513,32 @@ pub async fn serve(tx: Sender<ServiceProxy<'_>>) -> Result<()> {
 	let windows = Windows {
 		active_window: WindowDict::default(),
 		pointer_window: WindowDict::default(),
+		active_since: Instant::now(),
+		pointer_since: Instant::now(),
+		last_changed: 0,
+		focus_changes: 0,
+		allow_override: options.allow_override,
+		provider_host: options.provider_host,
+		provider_owner: None,
+		active_only: options.active_only,
+		sticky_active: options.sticky_active,
+		initialized: (false, !options.tracks_pointer),
+		ready: Some(ready),
+		recorder: options.recorder,
+		icons: HashMap::new(),
 	};
 
-	let connection = connection::Builder::session()?
+	let displays = Displays {
+		displays: Vec::new(),
+	};
+
+	let builder = if options.system_bus { connection::Builder::system()? } else { connection::Builder::session()? };
+
+	let connection = builder
 		.name("org.wctx")?
 		.serve_at("/", application)?
 		.serve_at("/", windows)?
+		.serve_at("/", displays)?
 		.build().await
 		.map_err(|err| match err {
 			zbus::Error::NameTaken => anyhow!("The daemon service is already running"),
@@ -141,6 +548,7 @@ pub async fn serve(tx: Sender<ServiceProxy<'_>>) -> Result<()> {
 	let service = ServiceProxy {
 		application: ApplicationProxy::new(&connection).await?,
 		windows: WindowsProxy::new(&connection).await?,
+		displays: DisplaysProxy::new(&connection).await?,
 	};
 
 	if tx.send(service).is_ok() {
@@ -0,0 +1,41 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use colored::Colorize;
+use futures_lite::stream::StreamExt;
+use zbus::{Connection, MatchRule, MessageStream};
+use zbus::message::Type as MessageType;
+use zbus::zvariant::Structure;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct Args {
+	/// Connect to a daemon serving on the system bus instead of the session bus
+	#[arg(long)]
+	system_bus: bool,
+}
+
+/// Print every signal sent by the daemon verbatim, for debugging providers
+pub async fn run(args: Args) -> Result<()> {
+	let connection = if args.system_bus { Connection::system().await? } else { Connection::session().await? };
+
+	// property changes are signals too, so this includes the window properties
+	let rule = MatchRule::builder()
+		.msg_type(MessageType::Signal)
+		.sender("org.wctx")?
+		.build();
+
+	let mut stream = MessageStream::for_match_rule(rule, &connection, None).await?;
+
+	while let Some(msg) = stream.try_next().await? {
+		let header = msg.header();
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+
+		let interface = header.interface().map(|name| name.to_string()).unwrap_or_default();
+		let member = header.member().map(|name| name.to_string()).unwrap_or_default();
+		// signals without arguments have an empty body
+		let payload = msg.body().deserialize::<Structure>().map(|body| body.to_string()).unwrap_or_default();
+
+		println!("{} {} {}", now.to_string().bright_black(), format!("{interface}.{member}").bright_blue(), payload);
+	}
+
+	Ok(())
+}
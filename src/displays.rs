@@ -0,0 +1,113 @@
+use crate::types::*;
+use crate::query::{connect_bus, connect_daemon, format_table, QueryFormat};
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use colored_json::to_colored_json_auto;
+use serde::Serialize;
+use zbus::proxy;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct Args {
+	/// Output format
+	#[arg(short, long, value_enum, default_value_t = QueryFormat::default())]
+	format: QueryFormat,
+
+	/// How long to keep retrying if the daemon isn't available yet (in milliseconds)
+	#[arg(long, value_name = "MS", default_value_t = 2000)]
+	connect_timeout: u64,
+
+	/// Connect to a daemon serving on the system bus instead of the session bus
+	#[arg(long)]
+	system_bus: bool,
+}
+
+#[derive(Serialize)]
+struct DisplayList<'a> {
+	displays: &'a [DisplayDict],
+}
+
+#[proxy(
+	interface = "org.wctx.Displays",
+	default_service = "org.wctx",
+	default_path = "/"
+)]
+trait Displays {
+	fn list_displays(&self) -> zbus::Result<Vec<DictMap<'_>>>;
+}
+
+pub async fn run(args: Args) -> Result<()> {
+	let connection = connect_bus(args.system_bus).await?;
+	connect_daemon(&connection, args.connect_timeout).await?;
+
+	let displays = DisplaysProxy::new(&connection).await?.list_displays().await?;
+
+	let displays = displays.into_iter()
+		.map(DisplayDict::try_from)
+		.collect::<Result<Vec<_>, _>>()?;
+
+	print!("{}", format(&displays, args.format)?);
+
+	Ok(())
+}
+
+fn format(displays: &[DisplayDict], format: QueryFormat) -> Result<String> {
+	let fields = |d: &DisplayDict| [
+		format!("{} {}", "name:".bright_blue(), d.name),
+		format!("{} {}", "x:".bright_blue(), d.x),
+		format!("{} {}", "y:".bright_blue(), d.y),
+		format!("{} {}", "width:".bright_blue(), d.width),
+		format!("{} {}", "height:".bright_blue(), d.height),
+		format!("{} {}", "primary:".bright_blue(), d.primary),
+		format!("{} {}", "scale:".bright_blue(), d.scale),
+		format!("{} {}", "dpi:".bright_blue(), d.dpi),
+		format!("{} {}", "refresh_rate:".bright_blue(), d.refresh_rate),
+	];
+
+	match format {
+		QueryFormat::Flat => {
+			Ok(displays.iter().map(|d| fields(d).join(&", ".bright_black()) + "\n").collect())
+		}
+		QueryFormat::Dict => {
+			Ok(displays.iter().map(|d| fields(d).join("\n") + "\n").collect::<Vec<_>>().join("\n"))
+		}
+		QueryFormat::Table => {
+			let header = ["name", "x", "y", "width", "height", "primary", "scale", "dpi", "refresh_rate"].map(String::from).to_vec();
+
+			let rows = displays.iter()
+				.map(|d| vec![
+					d.name.clone(),
+					d.x.to_string(),
+					d.y.to_string(),
+					d.width.to_string(),
+					d.height.to_string(),
+					d.primary.to_string(),
+					d.scale.to_string(),
+					d.dpi.to_string(),
+					d.refresh_rate.to_string(),
+				])
+				.collect();
+
+			Ok(format_table(Some(header), rows))
+		}
+		QueryFormat::TOML => {
+			toml::to_string(&DisplayList { displays }).map_err(|e| e.into())
+		}
+		QueryFormat::JSON => {
+			let value = serde_json::to_value(displays)?;
+			Ok(to_colored_json_auto(&value).unwrap_or_default() + "\n")
+		}
+		QueryFormat::CSV => {
+			let mut wtr = csv::WriterBuilder::new()
+				.from_writer(vec![]);
+
+			for display in displays {
+				wtr.serialize(display)?;
+			}
+
+			String::from_utf8(wtr.into_inner()?).map_err(|e| e.into())
+		}
+		QueryFormat::Waybar => {
+			Err(anyhow!("The waybar format isn't supported for displays"))
+		}
+	}
+}
@@ -1,7 +1,12 @@
 #[macro_use]
 mod types;
 mod daemon;
+mod debug;
+mod displays;
+mod providers;
 mod query;
+mod set;
+mod stack;
 
 use clap::Parser;
 use colored::Colorize;
@@ -14,27 +19,70 @@ struct Cli {
 
 	#[clap(flatten)]
 	args: query::Args,
+
+	/// Print errors to stderr as JSON objects with the message and exit code, for programs running wctx
+	#[arg(long, global = true)]
+	json_errors: bool,
 }
 
 #[derive(clap::Subcommand, Clone)]
 enum Command {
 	#[command(hide = true)]
 	Query(query::Args),
+	/// Print every signal sent by the daemon, for debugging window providers
+	#[command(hide = true)]
+	Debug(debug::Args),
 	Daemon(daemon::Args),
+	Set(set::Args),
+	/// List the displays (monitors) known to the daemon
+	Displays(displays::Args),
+	/// List the supported window providers and which one is detected
+	Providers(providers::Args),
+	/// Watch both the active and pointer windows, outputting each change tagged with its context
+	WatchAll(query::WatchAllArgs),
+	/// Raise a window above the other windows
+	Raise(stack::Args),
+	/// Lower a window below the other windows
+	Lower(stack::Args),
 }
 
 #[tokio::main]
 async fn main() {
 	let cli = Cli::parse();
+
+	if cli.json_errors {
+		// messages with colored hints would otherwise contain escape codes
+		colored::control::set_override(false);
+	}
+
 	let command = cli.command.unwrap_or_else(|| Command::Query(cli.args));
 
 	let result = match command {
 		Command::Query(args) => query::run(args).await,
+		Command::Debug(args) => debug::run(args).await,
 		Command::Daemon(args) => daemon::run(args).await,
+		Command::Set(args) => set::run(args).await,
+		Command::Displays(args) => displays::run(args).await,
+		Command::Providers(args) => providers::run(args).await,
+		Command::WatchAll(args) => query::watch_all(args).await,
+		Command::Raise(args) => stack::raise(args).await,
+		Command::Lower(args) => stack::lower(args).await,
 	};
 
 	if let Err(err) = result {
-		eprintln!("{} {}", "Error:".bright_red().bold(), err);
-		std::process::exit(1); // general error
+		let (code, silent) = match err.downcast_ref::<types::ExitError>() {
+			Some(exit) => (exit.code, exit.message.is_empty()),
+			None => (1, false), // general error
+		};
+
+		if silent {
+			// nothing to report, e.g. the result of `--exit-code`
+		} else if cli.json_errors {
+			eprintln!("{}", serde_json::json!({ "error": err.to_string(), "code": code }));
+		} else {
+			eprintln!("{} {}", "Error:".bright_red().bold(), err);
+		}
+
+		std::process::exit(code);
 	}
 }
@@ -0,0 +1,99 @@
+use crate::daemon::{detect_provider, WindowProvider, DETECT_ENV_VARS};
+use crate::query::{format_table, QueryFormat};
+use std::collections::BTreeMap;
+use std::env;
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use colored_json::to_colored_json_auto;
+use serde::Serialize;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct Args {
+	/// Output format
+	#[arg(short, long, value_enum, default_value_t = QueryFormat::default())]
+	format: QueryFormat,
+}
+
+#[derive(Serialize)]
+struct ProviderInfo {
+	/// The provider the daemon would use, or empty if none was detected
+	provider: String,
+	available: Vec<String>,
+	env: BTreeMap<String, String>,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+	let info = ProviderInfo {
+		provider: detect_provider().map(provider_name).unwrap_or_default(),
+		available: WindowProvider::value_variants().iter().copied().map(provider_name).collect(),
+		env: DETECT_ENV_VARS.iter()
+			.map(|var| (var.to_string(), env::var(var).unwrap_or_default()))
+			.collect(),
+	};
+
+	print!("{}", format(&info, args.format)?);
+
+	Ok(())
+}
+
+/// Name of the provider as accepted by `wctx daemon --provider`
+fn provider_name(provider: WindowProvider) -> String {
+	provider.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default()
+}
+
+fn format(info: &ProviderInfo, format: QueryFormat) -> Result<String> {
+	let provider = if info.provider.is_empty() { "none" } else { &info.provider };
+
+	match format {
+		QueryFormat::Flat | QueryFormat::Dict => {
+			let mut fields = vec![
+				format!("{} {}", "provider:".bright_blue(), provider),
+				format!("{} {}", "available:".bright_blue(), info.available.join(" ")),
+			];
+
+			fields.extend(info.env.iter().map(|(var, value)| format!("{} {}", format!("{}:", var).bright_blue(), value)));
+
+			if let QueryFormat::Flat = format {
+				Ok(fields.join(&", ".bright_black()) + "\n")
+			} else {
+				Ok(fields.join("\n") + "\n")
+			}
+		}
+		QueryFormat::Table => {
+			let mut rows = vec![
+				vec!["provider".to_string(), provider.to_string()],
+				vec!["available".to_string(), info.available.join(" ")],
+			];
+
+			rows.extend(info.env.iter().map(|(var, value)| vec![var.clone(), value.clone()]));
+
+			Ok(format_table(None, rows))
+		}
+		QueryFormat::TOML => {
+			toml::to_string(info).map_err(|e| e.into())
+		}
+		QueryFormat::JSON => {
+			let value = serde_json::to_value(info)?;
+			Ok(to_colored_json_auto(&value).unwrap_or_default() + "\n")
+		}
+		QueryFormat::CSV => {
+			let mut wtr = csv::WriterBuilder::new()
+				.from_writer(vec![]);
+
+			let mut header = vec!["provider", "available"];
+			header.extend(info.env.keys().map(String::as_str));
+			wtr.write_record(header)?;
+
+			let available = info.available.join(" ");
+			let mut row = vec![info.provider.as_str(), available.as_str()];
+			row.extend(info.env.values().map(String::as_str));
+			wtr.write_record(row)?;
+
+			String::from_utf8(wtr.into_inner()?).map_err(|e| e.into())
+		}
+		QueryFormat::Waybar => {
+			Err(anyhow!("The waybar format isn't supported for providers"))
+		}
+	}
+}
@@ -1,8 +1,14 @@
 use crate::types::*;
+use crate::daemon::Debouncer;
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::io::{self, Write};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use anyhow::{anyhow, Result};
-use colored::Colorize;
+use clap::builder::PossibleValue;
+use clap::ValueEnum;
+use colored::{Color, Colorize};
 use colored_json::to_colored_json_auto;
 use futures_lite::stream::StreamExt;
 use serde::Serialize;
@@ -14,22 +20,198 @@ pub struct Args {
 	#[arg(required = true)]
 	context: Option<QueryContext>,
 
-	/// Query a single property value
-	property: Option<WindowProp>,
+	#[command(flatten)]
+	output: OutputArgs,
 
-	/// Output format
-	#[arg(short, long, value_enum, default_value_t = QueryFormat::default())]
-	format: QueryFormat,
+	/// Properties to leave out of the output, comma-separated (e.g. "title,role")
+	#[arg(short = 'x', long, value_name = "PROPS", value_delimiter = ',', conflicts_with = "property")]
+	exclude: Vec<WindowProp>,
 
 	/// Monitor and output window changes
 	#[arg(short, long)]
 	watch: bool,
+
+	/// Output every window change when watching, even if nothing in the output changed
+	#[arg(long, requires = "watch")]
+	no_dedupe: bool,
+
+	/// Only output changes when watching, without the current window first
+	#[arg(long, requires = "watch")]
+	no_initial: bool,
+
+	/// Exit if the daemon stops while watching, instead of waiting for it to restart
+	#[arg(long, requires = "watch")]
+	no_reconnect: bool,
+
+	/// Wait until changes settle for MS milliseconds when watching, then only output the latest window (0 to output every change)
+	#[arg(long, value_name = "MS", default_value_t = 0, requires = "watch")]
+	buffer: u64,
+
+	/// Only output windows whose property equals a value when watching, e.g. "state=fullscreen" (can be repeated)
+	#[arg(long, value_name = "PROP=VALUE", value_parser = parse_filter, requires = "watch")]
+	filter: Vec<(WindowProp, String)>,
+
+	/// Query the window provider directly without the daemon (X11 only)
+	#[arg(long)]
+	standalone: bool,
+
+	/// Value to output when the property (or window) is empty
+	#[arg(long, value_name = "VALUE")]
+	default: Option<String>,
+
+	/// Exit with an error when there is no window in the queried context (including any change while watching)
+	#[arg(long, conflicts_with = "default")]
+	strict: bool,
+
+	/// Print nothing and exit with 0 if the property equals VALUE, or 1 otherwise (waits for a match with --watch)
+	#[arg(long, value_name = "VALUE", requires = "property")]
+	exit_code: Option<String>,
+
+	/// Field delimiter for the CSV format (a single character, or "\t" for tab)
+	#[arg(long, value_name = "CHAR", default_value = ",", value_parser = parse_csv_delimiter)]
+	csv_delimiter: u8,
+
+	/// Add an `event` field to the JSON format, which is "initial" for the current window and "change" for changes when watching
+	#[arg(long)]
+	with_event: bool,
+
+	/// Output the TOML format with long strings and arrays spread over multiple lines
+	#[arg(long)]
+	pretty: bool,
+
+	/// Output only the values, one per line, without labels or colors (overrides --format)
+	#[arg(long)]
+	raw: bool,
+
+	/// Color-code state and type values in the flat and dict formats (e.g. fullscreen in red)
+	#[arg(long)]
+	highlight: bool,
+
+	/// Truncate titles longer than N characters, ending them with an ellipsis
+	#[arg(long, value_name = "N")]
+	max_title_len: Option<usize>,
+
+	/// Output numeric window IDs in hexadecimal (e.g. 0x3a00007, like xprop and wmctrl)
+	#[arg(long, conflicts_with = "decimal")]
+	hex: bool,
+
+	/// Output numeric window IDs in decimal (like xdotool)
+	#[arg(long)]
+	decimal: bool,
+
+	/// How long to keep retrying if the daemon isn't available yet (in milliseconds)
+	#[arg(long, value_name = "MS", default_value_t = 2000)]
+	connect_timeout: u64,
+
+	/// Connect to a daemon serving on the system bus instead of the session bus
+	#[arg(long)]
+	system_bus: bool,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct WatchAllArgs {
+	#[command(flatten)]
+	output: OutputArgs,
+
+	/// Output every window change, even if nothing in the output changed
+	#[arg(long)]
+	no_dedupe: bool,
+
+	/// How long to keep retrying if the daemon isn't available yet (in milliseconds)
+	#[arg(long, value_name = "MS", default_value_t = 2000)]
+	connect_timeout: u64,
+
+	/// Connect to a daemon serving on the system bus instead of the session bus
+	#[arg(long)]
+	system_bus: bool,
+}
+
+/// Output options shared by queries and `watch-all`
+#[derive(clap::Args, Clone, Debug)]
+pub struct OutputArgs {
+	/// Query a single property value
+	property: Option<QueryKey>,
+
+	/// Output format (only flat, dict or json for `watch-all`)
+	#[arg(short, long, value_enum, env = "WCTX_FORMAT", default_value_t = QueryFormat::default())]
+	format: QueryFormat,
+
+	/// Include a timestamp (in epoch milliseconds) with each output
+	#[arg(short, long)]
+	timestamp: bool,
+
+	/// Separator between properties in the flat format
+	#[arg(long, value_name = "STR", default_value = ", ")]
+	separator: String,
+
+	/// Quote and escape titles, and any values containing the separator or control characters, in the flat format
+	#[arg(long)]
+	quote: bool,
+
+	/// Output the JSON format on a single line without colors, e.g. for logs or NDJSON
+	#[arg(long)]
+	compact: bool,
+}
+
+fn parse_filter(value: &str) -> Result<(WindowProp, String), String> {
+	let (key, value) = value.split_once('=').ok_or("expected PROP=VALUE")?;
+	let key = WindowProp::from_str(key, true)?;
+
+	Ok((key, value.to_string()))
+}
+
+fn parse_csv_delimiter(value: &str) -> Result<u8, String> {
+	match value.as_bytes() {
+		b"\\t" => Ok(b'\t'),
+		[byte] => Ok(*byte),
+		_ => Err("expected a single byte character".into()),
+	}
+}
+
+/// A property to query, either of the window or comparing the windows of the `both` context
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum QueryKey {
+	Window(WindowProp),
+	/// Whether the active and pointer windows are the same (`both` context only)
+	Same,
+}
+
+impl ValueEnum for QueryKey {
+	fn value_variants<'a>() -> &'a [Self] {
+		static VARIANTS: OnceLock<Vec<QueryKey>> = OnceLock::new();
+
+		VARIANTS.get_or_init(|| {
+			WindowProp::value_variants().iter()
+				.map(|prop| Self::Window(*prop))
+				.chain([Self::Same])
+				.collect()
+		})
+	}
+
+	fn to_possible_value(&self) -> Option<PossibleValue> {
+		match self {
+			Self::Window(prop) => prop.to_possible_value(),
+			Self::Same => Some(PossibleValue::new("same").help("Whether the active and pointer windows are the same (`both` context only)")),
+		}
+	}
+}
+
+impl Display for QueryKey {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Window(prop) => write!(f, "{}", prop),
+			Self::Same => write!(f, "same"),
+		}
+	}
 }
 
 #[derive(Copy, Clone, Debug, clap::ValueEnum)]
 pub enum QueryContext {
 	Active,
 	Pointer,
+	Both,
+	/// Window with the X input focus, which can differ from the active window (X11 only, can't be watched)
+	Focus,
 }
 
 #[derive(Copy, Clone, Default, Debug, clap::ValueEnum, strum::Display)]
@@ -38,9 +220,12 @@ pub enum QueryFormat {
 	#[default]
 	Flat,
 	Dict,
+	/// Like dict, but with aligned columns
+	Table,
 	JSON,
 	TOML,
 	CSV,
+	Waybar,
 }
 
 #[derive(Debug, Serialize)]
@@ -49,12 +234,48 @@ enum QueryProp<'a> {
 	ID(&'a str),
 	Name(&'a str),
 	Class(&'a str),
+	Icon(&'a str),
 	PID(u32),
+	Comm(&'a str),
 	Title(&'a str),
 	Type(WindowType),
 	Role(&'a str),
+	Leader(&'a str),
 	State(WindowState),
+	States(&'a str),
+	Decorated(bool),
+	AcceptsFocus(bool),
 	Display(&'a str),
+	#[serde(rename = "display_index")]
+	DisplayIndex(u32),
+	X(i32),
+	Y(i32),
+	Width(u32),
+	Height(u32),
+	Workspace(&'a str),
+	Stack(i32),
+	#[serde(rename = "user_time")]
+	UserTime(u32),
+	Depth(u32),
+	Visual(u32),
+	Age(u32),
+	Same(bool),
+}
+
+/// A substitute value for an empty property, serialized the same way as a `QueryProp`
+#[derive(Debug)]
+struct DefaultProp<'a>(WindowProp, &'a str);
+
+impl Serialize for DefaultProp<'_> {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		serializer.serialize_newtype_variant("QueryProp", self.0 as u32, self.0.into(), self.1)
+	}
+}
+
+impl Display for DefaultProp<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.1)
+	}
 }
 
 impl Display for QueryProp<'_> {
@@ -63,12 +284,168 @@ impl Display for QueryProp<'_> {
 			Self::ID(v) => write!(f, "{}", v),
 			Self::Name(v) => write!(f, "{}", v),
 			Self::Class(v) => write!(f, "{}", v),
+			Self::Icon(v) => write!(f, "{}", v),
 			Self::PID(v) => write!(f, "{}", v),
+			Self::Comm(v) => write!(f, "{}", v),
 			Self::Title(v) => write!(f, "{}", v),
 			Self::Type(v) => write!(f, "{}", v),
 			Self::Role(v) => write!(f, "{}", v),
+			Self::Leader(v) => write!(f, "{}", v),
 			Self::State(v) => write!(f, "{}", v),
+			Self::States(v) => write!(f, "{}", v),
+			Self::Decorated(v) => write!(f, "{}", v),
+			Self::AcceptsFocus(v) => write!(f, "{}", v),
 			Self::Display(v) => write!(f, "{}", v),
+			Self::DisplayIndex(v) => write!(f, "{}", v),
+			Self::X(v) => write!(f, "{}", v),
+			Self::Y(v) => write!(f, "{}", v),
+			Self::Width(v) => write!(f, "{}", v),
+			Self::Height(v) => write!(f, "{}", v),
+			Self::Workspace(v) => write!(f, "{}", v),
+			Self::Stack(v) => write!(f, "{}", v),
+			Self::UserTime(v) => write!(f, "{}", v),
+			Self::Depth(v) => write!(f, "{}", v),
+			Self::Visual(v) => write!(f, "{}", v),
+			Self::Age(v) => write!(f, "{}", v),
+			Self::Same(v) => write!(f, "{}", v),
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+struct BothWindows<T> {
+	active: T,
+	pointer: T,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	same: Option<bool>,
+}
+
+/// A change to a watched window, either the whole window or a single property from its own signal
+enum WindowUpdate {
+	Window(WindowDict),
+	Prop(WindowProp, String),
+}
+
+enum QueryWindow {
+	Single(WindowDict),
+	Both(WindowDict, WindowDict),
+}
+
+/// Base to output numeric window IDs in
+#[derive(Copy, Clone)]
+enum IdFormat {
+	Decimal,
+	Hex,
+}
+
+impl QueryWindow {
+	fn new(context: QueryContext, active: &WindowDict, pointer: &WindowDict) -> Self {
+		match context {
+			QueryContext::Active | QueryContext::Focus => Self::Single(active.clone()),
+			QueryContext::Pointer => Self::Single(pointer.clone()),
+			QueryContext::Both => Self::Both(active.clone(), pointer.clone()),
+		}
+	}
+
+	/// Rewrite numeric window IDs in the given base, leaving others (e.g. KWin UUIDs) as they are
+	fn format_ids(&mut self, id_format: IdFormat) {
+		let windows = match self {
+			Self::Single(window) => vec![window],
+			Self::Both(active, pointer) => vec![active, pointer],
+		};
+
+		for window in windows {
+			for value in [&mut window.id, &mut window.leader] {
+				let id = match value.strip_prefix("0x") {
+					Some(hex) => u64::from_str_radix(hex, 16),
+					None => value.parse(),
+				};
+
+				if let Ok(id) = id {
+					*value = match id_format {
+						IdFormat::Decimal => id.to_string(),
+						IdFormat::Hex => format!("{id:#x}"),
+					};
+				}
+			}
+		}
+	}
+
+	/// Shorten titles to at most `max_len` characters (not bytes, so multibyte characters aren't split), including the ellipsis
+	fn truncate_titles(&mut self, max_len: usize) {
+		let windows = match self {
+			Self::Single(window) => vec![window],
+			Self::Both(active, pointer) => vec![active, pointer],
+		};
+
+		for window in windows {
+			if window.title.chars().count() > max_len {
+				window.title = window.title.chars().take(max_len.saturating_sub(1)).collect();
+
+				if max_len > 0 {
+					window.title.push('…');
+				}
+			}
+		}
+	}
+
+	/// Whether the property equals the value (ignoring case, e.g. for `fullscreen`), in either window for the `both` context
+	fn matches(&self, key: WindowProp, value: &str) -> bool {
+		let windows = match self {
+			Self::Single(window) => vec![window],
+			Self::Both(active, pointer) => vec![active, pointer],
+		};
+
+		windows.iter().any(|window| window.prop(key).to_string().eq_ignore_ascii_case(value))
+	}
+
+	/// Whether there is no window in the queried context (either of them for the `both` context)
+	fn is_empty(&self) -> bool {
+		match self {
+			Self::Single(window) => window.id.is_empty(),
+			Self::Both(active, pointer) => active.id.is_empty() || pointer.id.is_empty(),
+		}
+	}
+
+	fn prop_string(&self, key: QueryKey) -> String {
+		match (self, key) {
+			(Self::Single(window), QueryKey::Window(key)) => window.prop(key).to_string(),
+			(Self::Both(active, pointer), QueryKey::Same) => QueryProp::Same(active.id == pointer.id).to_string(),
+			// other combinations are rejected before querying
+			_ => String::new(),
+		}
+	}
+
+	fn same_ids(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Self::Single(a), Self::Single(b)) => a.id == b.id,
+			(Self::Both(a1, p1), Self::Both(a2, p2)) => a1.id == a2.id && p1.id == p2.id,
+			_ => false,
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+struct WaybarOutput<'a> {
+	text: String,
+	tooltip: String,
+	class: &'a str,
+	alt: &'a str,
+}
+
+impl<'a> WaybarOutput<'a> {
+	fn new(window: &'a WindowDict, text: String) -> Self {
+		let tooltip = match (window.class.as_str(), window.title.as_str()) {
+			("", title) => title.to_string(),
+			(class, "") => class.to_string(),
+			(class, title) => format!("{class}: {title}"),
+		};
+
+		Self {
+			text,
+			tooltip,
+			class: &window.class,
+			alt: &window.name,
 		}
 	}
 }
@@ -79,152 +456,637 @@ impl WindowDict {
 			WindowProp::ID => QueryProp::ID(&self.id),
 			WindowProp::Name => QueryProp::Name(&self.name),
 			WindowProp::Class => QueryProp::Class(&self.class),
+			WindowProp::Icon => QueryProp::Icon(&self.icon),
 			WindowProp::PID => QueryProp::PID(self.pid),
+			WindowProp::Comm => QueryProp::Comm(&self.comm),
 			WindowProp::Title => QueryProp::Title(&self.title),
 			WindowProp::Type => QueryProp::Type(self.r#type),
 			WindowProp::Role => QueryProp::Role(&self.role),
+			WindowProp::Leader => QueryProp::Leader(&self.leader),
 			WindowProp::State => QueryProp::State(self.state),
+			WindowProp::States => QueryProp::States(&self.states),
+			WindowProp::Decorated => QueryProp::Decorated(self.decorated),
+			WindowProp::AcceptsFocus => QueryProp::AcceptsFocus(self.accepts_focus),
 			WindowProp::Display => QueryProp::Display(&self.display),
+			WindowProp::DisplayIndex => QueryProp::DisplayIndex(self.display_index),
+			WindowProp::X => QueryProp::X(self.x),
+			WindowProp::Y => QueryProp::Y(self.y),
+			WindowProp::Width => QueryProp::Width(self.width),
+			WindowProp::Height => QueryProp::Height(self.height),
+			WindowProp::Workspace => QueryProp::Workspace(&self.workspace),
+			WindowProp::Stack => QueryProp::Stack(self.stack),
+			WindowProp::UserTime => QueryProp::UserTime(self.user_time),
+			WindowProp::Depth => QueryProp::Depth(self.depth),
+			WindowProp::Visual => QueryProp::Visual(self.visual),
+			WindowProp::Age => QueryProp::Age(self.age),
 		}
 	}
+
+	fn props(&self) -> impl Iterator<Item = (WindowProp, QueryProp)> {
+		WindowProp::value_variants().iter().map(|key| (*key, self.prop(*key)))
+	}
 }
 
 struct Printer {
-	window: Option<WindowDict>,
-	property: Option<WindowProp>,
+	window: Option<QueryWindow>,
+	property: Option<QueryKey>,
 	format: QueryFormat,
+	exclude: Vec<WindowProp>,
+	default: Option<String>,
+	strict: bool,
+	timestamp: bool,
+	separator: String,
+	quote: bool,
+	csv_delimiter: u8,
+	compact: bool,
+	with_event: bool,
+	/// Event of the output being formatted, only set once it's known to be printed
+	event: Option<&'static str>,
+	pretty: bool,
+	/// Output values without labels, one per line
+	raw: bool,
+	highlight: bool,
+	max_title_len: Option<usize>,
+	id_format: Option<IdFormat>,
+	now: Option<u64>,
 	output: String,
 	linebreak: bool,
 	first: bool,
+	/// Whether the next output is the initial window rather than a change
+	initial: bool,
+	exit_code: Option<String>,
+	filter: Vec<(WindowProp, String)>,
+	watch: bool,
+	dedupe: bool,
+	skip_initial: bool,
+	/// Context to tag each output with, for `watch-all`
+	tag: Option<&'static str>,
 }
 
 impl Printer {
-	fn new(property: Option<WindowProp>, format: QueryFormat, watch: bool) -> Self {
-		let linebreak = if property.is_some() {
+	fn new(args: &Args) -> Self {
+		Self {
+			exclude: args.exclude.clone(),
+			default: args.default.clone(),
+			strict: args.strict,
+			csv_delimiter: args.csv_delimiter,
+			with_event: args.with_event,
+			pretty: args.pretty,
+			raw: args.raw,
+			highlight: args.highlight,
+			max_title_len: args.max_title_len,
+			id_format: if args.hex {
+				Some(IdFormat::Hex)
+			} else if args.decimal {
+				Some(IdFormat::Decimal)
+			} else {
+				None
+			},
+			initial: !args.no_initial,
+			exit_code: args.exit_code.clone(),
+			filter: args.filter.clone(),
+			skip_initial: args.no_initial,
+			..Self::with_output(&args.output, args.watch, !args.no_dedupe)
+		}
+	}
+
+	/// A printer with only the shared output options set
+	fn with_output(output: &OutputArgs, watch: bool, dedupe: bool) -> Self {
+		let (property, format) = (output.property, output.format);
+
+		let linebreak = if matches!(format, QueryFormat::Waybar) {
+			true
+		} else if property.is_some() {
 			!matches!(format, QueryFormat::TOML | QueryFormat::CSV)
 		} else {
-			watch && matches!(format, QueryFormat::Dict | QueryFormat::JSON | QueryFormat::TOML)
+			watch && matches!(format, QueryFormat::Dict | QueryFormat::Table | QueryFormat::JSON | QueryFormat::TOML)
 		};
 
 		Self {
 			window: None,
 			property,
 			format,
+			exclude: Vec::new(),
+			default: None,
+			strict: false,
+			timestamp: output.timestamp,
+			separator: output.separator.clone(),
+			quote: output.quote,
+			csv_delimiter: b',',
+			compact: output.compact,
+			with_event: false,
+			event: None,
+			pretty: false,
+			raw: false,
+			highlight: false,
+			max_title_len: None,
+			id_format: None,
+			now: None,
 			output: Default::default(),
 			linebreak,
 			first: true,
+			initial: true,
+			exit_code: None,
+			filter: Vec::new(),
+			watch,
+			dedupe,
+			skip_initial: false,
+			tag: None,
 		}
 	}
 
-	fn print(&mut self, window: WindowDict) {
-		let Ok(output) = self.format(&window) else {
-			return;
+	fn tagged(self, tag: &'static str) -> Self {
+		Self { tag: Some(tag), ..self }
+	}
+
+	fn print(&mut self, mut window: QueryWindow) -> Result<()> {
+		if self.strict && window.is_empty() {
+			return Err(ExitError::new(1, "There is no window in the queried context").into());
+		}
+
+		if let Some(id_format) = self.id_format {
+			window.format_ids(id_format);
+		}
+
+		if let Some(max_len) = self.max_title_len {
+			window.truncate_titles(max_len);
+		}
+
+		// the initial window is only remembered, so the first change is deduped against it
+		if self.skip_initial {
+			self.skip_initial = false;
+			self.output = self.format_query(&window).unwrap_or_default();
+			self.window = Some(window);
+			return Ok(());
+		}
+
+		if !self.filter.iter().all(|(key, value)| window.matches(*key, value)) {
+			// forget the last output, so the next matching window is output even if it's unchanged since then
+			self.output.clear();
+			return Ok(());
+		}
+
+		if let (Some(expected), Some(key)) = (&self.exit_code, self.property) {
+			let matched = window.prop_string(key) == *expected;
+
+			// when watching, keep waiting until the property matches
+			if matched || !self.watch {
+				return Err(ExitError::silent(if matched { 0 } else { 1 }).into());
+			}
+
+			return Ok(());
+		}
+
+		let Ok(output) = self.format_query(&window) else {
+			return Ok(());
 		};
 
 		let mut print = true;
 
 		if let Some(last_window) = &self.window {
-			if window.id == last_window.id && output == self.output {
+			if self.dedupe && window.same_ids(last_window) && output == self.output {
 				print = false;
 			}
 		}
 
-		self.window = Some(window);
-
 		if print {
 			self.output = output;
+
+			// only timestamp real changes (and tag their event), so format again now that we know this will be printed
+			let output = if self.timestamp || self.with_event {
+				if self.timestamp {
+					self.now = Some(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64);
+				}
+
+				if self.with_event {
+					self.event = Some(if self.initial { "initial" } else { "change" });
+				}
+
+				let output = self.format_query(&window).unwrap_or_else(|_| self.output.clone());
+				self.now = None;
+				self.event = None;
+				output
+			} else {
+				self.output.clone()
+			};
+
 			self.first = false;
+			self.initial = false;
 
 			if self.linebreak {
-				println!("{}", self.output);
+				println!("{}", output);
 			} else {
-				print!("{}", self.output);
-				io::stdout().flush().unwrap();
+				print!("{}", output);
+				io::stdout().flush()?;
 			}
 		}
+
+		self.window = Some(window);
+
+		Ok(())
+	}
+
+	fn format_query(&self, window: &QueryWindow) -> Result<String> {
+		let output = match window {
+			QueryWindow::Single(window) => self.format(window),
+			QueryWindow::Both(active, pointer) => self.format_both(active, pointer),
+		}?;
+
+		// the tag is added to the JSON object in `to_json`
+		let output = match (self.tag, self.format) {
+			(Some(tag), QueryFormat::Flat | QueryFormat::Dict) => {
+				output.split_inclusive('\n')
+					.map(|line| format!("{} {line}", tag.bright_magenta()))
+					.collect()
+			}
+			_ => output,
+		};
+
+		let Some(now) = self.now else {
+			return Ok(output);
+		};
+
+		Ok(match self.format {
+			QueryFormat::Flat | QueryFormat::Dict | QueryFormat::Table => {
+				output.split_inclusive('\n')
+					.map(|line| format!("{} {line}", now.to_string().bright_black()))
+					.collect()
+			}
+			QueryFormat::TOML => {
+				// top-level values must come before any tables, so the start is always valid
+				format!("timestamp = {now}\n{output}")
+			}
+			QueryFormat::CSV => {
+				let delimiter = self.csv_delimiter as char;
+
+				match output.split_once('\n') {
+					Some((header, rest)) if self.first => format!("timestamp{delimiter}{header}\n{now}{delimiter}{rest}"),
+					_ => format!("{now}{delimiter}{output}"),
+				}
+			}
+			// timestamp is added to the JSON object in `to_json`
+			QueryFormat::JSON | QueryFormat::Waybar => output,
+		})
+	}
+
+	fn to_json<T: Serialize>(&self, value: T) -> Result<String> {
+		let mut value = serde_json::to_value(value)?;
+
+		// single property values need an object to be tagged
+		if (self.tag.is_some() || self.event.is_some()) && !value.is_object() {
+			let mut map = serde_json::Map::new();
+			map.insert(self.property.map(|key| key.to_string()).unwrap_or_default(), value);
+			value = map.into();
+		}
+
+		if let (Some(tag), serde_json::Value::Object(map)) = (self.tag, &mut value) {
+			map.insert("context".into(), tag.into());
+		}
+
+		if let (Some(event), serde_json::Value::Object(map)) = (self.event, &mut value) {
+			map.insert("event".into(), event.into());
+		}
+
+		if let (Some(now), serde_json::Value::Object(map)) = (self.now, &mut value) {
+			map.insert("timestamp".into(), now.into());
+		}
+
+		if self.compact {
+			return serde_json::to_string(&value).map_err(|e| e.into());
+		}
+
+		Ok(to_colored_json_auto(&value).unwrap_or_default())
+	}
+
+	fn to_toml<T: Serialize>(&self, value: &T) -> Result<String> {
+		if self.pretty {
+			return toml::to_string_pretty(value).map_err(|e| e.into());
+		}
+
+		toml::to_string(value).map_err(|e| e.into())
 	}
 
 	fn format(&self, window: &WindowDict) -> Result<String> {
-		if let Some(key) = self.property {
+		if let Some(QueryKey::Window(key)) = self.property {
 			let prop = window.prop(key);
 
-			match self.format {
-				QueryFormat::Flat => {
-					Ok(prop.to_string())
+			match &self.default {
+				Some(default) if window.id.is_empty() || prop.to_string().is_empty() => {
+					self.format_prop(window, QueryKey::Window(key), DefaultProp(key, default))
 				}
-				QueryFormat::Dict => {
-					Ok(format!("{} {prop}", format!("{key}:").bright_blue()))
-				}
-				QueryFormat::TOML => {
-					toml::to_string(&prop).map_err(|e| e.into())
+				_ => self.format_prop(window, QueryKey::Window(key), prop),
+			}
+		} else {
+			if let Some(default) = &self.default {
+				if window.id.is_empty() {
+					return Ok(if self.linebreak { default.clone() } else { format!("{default}\n") });
 				}
-				QueryFormat::JSON => {
-					serde_json::to_value(prop)
-						.map(|v| to_colored_json_auto(&v).unwrap_or_default())
-						.map_err(|e| e.into())
+			}
+
+			self.format_window(window)
+		}
+	}
+
+	fn format_prop<P: Serialize + Display>(&self, window: &WindowDict, key: QueryKey, prop: P) -> Result<String> {
+		// `same` isn't a property of the window, and never color-coded
+		let highlight = |value: String| match key {
+			QueryKey::Window(key) => self.highlight(&window.prop(key), value),
+			QueryKey::Same => value,
+		};
+
+		match self.format {
+			QueryFormat::Flat => {
+				Ok(highlight(self.quote_flat(prop.to_string(), key == QueryKey::Window(WindowProp::Title))))
+			}
+			QueryFormat::Dict => {
+				Ok(format!("{} {}", format!("{key}:").bright_blue(), highlight(prop.to_string())))
+			}
+			QueryFormat::Table => {
+				Ok(format!("{} {prop}", format!("{key}:").bright_blue()))
+			}
+			QueryFormat::TOML => {
+				// a bare value isn't a valid TOML document, so output it as a key/value pair
+				self.to_toml(&HashMap::from([(key.to_string(), prop)]))
+			}
+			QueryFormat::JSON => {
+				self.to_json(prop)
+			}
+			QueryFormat::CSV => {
+				let mut wtr = csv::WriterBuilder::new()
+					.has_headers(false)
+					.delimiter(self.csv_delimiter)
+					.from_writer(Vec::new());
+
+				if self.first {
+					wtr.serialize(key.to_string())?;
 				}
-				QueryFormat::CSV => {
-					let mut wtr = csv::WriterBuilder::new()
-						.has_headers(false)
-						.from_writer(Vec::new());
 
-					if self.first {
-						wtr.serialize(key)?;
-					}
+				wtr.serialize(prop)?;
+				String::from_utf8(wtr.into_inner()?).map_err(|e| e.into())
+			}
+			QueryFormat::Waybar => {
+				serde_json::to_string(&WaybarOutput::new(window, prop.to_string())).map_err(|e| e.into())
+			}
+		}
+	}
 
-					wtr.serialize(prop)?;
-					String::from_utf8(wtr.into_inner()?).map_err(|e| e.into())
+	fn format_window(&self, window: &WindowDict) -> Result<String> {
+		match self.format {
+			QueryFormat::Flat => {
+				Ok(self.join_flat(&self.labeled_props(window, None)) + "\n")
+			}
+			QueryFormat::Dict => {
+				Ok(self.join_dict(&self.labeled_props(window, None), "\n") + "\n")
+			}
+			QueryFormat::Table => {
+				Ok(format_table(None, self.props(window).map(|(key, prop)| vec![key.to_string(), prop.to_string()]).collect()))
+			}
+			QueryFormat::TOML if self.exclude.is_empty() => {
+				self.to_toml(window)
+			}
+			QueryFormat::TOML => {
+				self.to_toml(&self.window_value(window)?)
+			}
+			QueryFormat::JSON => {
+				self.to_json(self.window_value(window)?)
+			}
+			QueryFormat::CSV => {
+				let props = self.labeled_props(window, None);
+
+				let mut wtr = csv::WriterBuilder::new()
+					.delimiter(self.csv_delimiter)
+					.from_writer(vec![]);
+
+				if self.first {
+					wtr.write_record(props.iter().map(|(key, _)| key))?;
 				}
+
+				wtr.write_record(props.iter().map(|(_, prop)| prop.to_string()))?;
+				String::from_utf8(wtr.into_inner()?).map_err(|e| e.into())
+			}
+			QueryFormat::Waybar => {
+				serde_json::to_string(&WaybarOutput::new(window, window.title.clone())).map_err(|e| e.into())
 			}
+		}
+	}
+
+	/// Quote a value for the flat format if `--quote` is set and the value could be mistaken for several values
+	fn quote_flat(&self, value: String, is_title: bool) -> String {
+		let needs_quotes = is_title
+			|| (!self.separator.is_empty() && value.contains(&self.separator))
+			|| value.contains(|c: char| c.is_control() || c == '"' || c == '\\');
+
+		if self.quote && needs_quotes {
+			// debug formatting wraps in double quotes and escapes quotes, backslashes and control characters
+			format!("{value:?}")
 		} else {
-			match self.format {
-				QueryFormat::Flat => {
-					Ok([
-						format!("{} {}", "id:".bright_blue(), window.id),
-						format!("{} {}", "name:".bright_blue(), window.name),
-						format!("{} {}", "class:".bright_blue(), window.class),
-						format!("{} {}", "pid:".bright_blue(), window.pid),
-						format!("{} {}", "title:".bright_blue(), window.title),
-						format!("{} {}", "type:".bright_blue(), window.r#type),
-						format!("{} {}", "role:".bright_blue(), window.role),
-						format!("{} {}", "state:".bright_blue(), window.state),
-						format!("{} {}", "display:".bright_blue(), window.display),
-					].join(&", ".bright_black()) + "\n")
+			value
+		}
+	}
+
+	fn quote_prop(&self, prop: &QueryProp) -> String {
+		self.quote_flat(prop.to_string(), matches!(prop, QueryProp::Title(_)))
+	}
+
+	fn join_flat(&self, props: &[(String, QueryProp)]) -> String {
+		if self.raw {
+			return props.iter().map(|(_, prop)| self.quote_prop(prop)).collect::<Vec<_>>().join("\n");
+		}
+
+		props.iter()
+			.map(|(key, prop)| format!("{} {}", format!("{key}:").bright_blue(), self.highlight(prop, self.quote_prop(prop))))
+			.collect::<Vec<_>>()
+			.join(&self.separator.bright_black())
+	}
+
+	fn join_dict(&self, props: &[(String, QueryProp)], sep: &str) -> String {
+		props.iter()
+			.map(|(key, prop)| format!("{} {}", format!("{key}:").bright_blue(), self.highlight(prop, prop.to_string())))
+			.collect::<Vec<_>>()
+			.join(sep)
+	}
+
+	/// Color-code state and type values with `--highlight`, which are left plain when colors are disabled
+	fn highlight(&self, prop: &QueryProp, value: String) -> String {
+		if !self.highlight {
+			return value;
+		}
+
+		let color = match prop {
+			QueryProp::State(WindowState::Fullscreen) => Color::Red,
+			QueryProp::State(WindowState::Maximized) => Color::Yellow,
+			QueryProp::State(WindowState::Tiled) => Color::Cyan,
+			QueryProp::State(WindowState::Floating) => Color::Magenta,
+			QueryProp::State(WindowState::Shaded) => Color::Blue,
+			QueryProp::Type(WindowType::Dialog | WindowType::Utility | WindowType::Toolbar) => Color::Yellow,
+			QueryProp::Type(WindowType::Menu | WindowType::DropdownMenu | WindowType::PopupMenu | WindowType::Combo | WindowType::Tooltip) => Color::Magenta,
+			QueryProp::Type(WindowType::Notification | WindowType::Splash) => Color::Cyan,
+			QueryProp::Type(WindowType::Dock | WindowType::Desktop | WindowType::DND) => Color::Blue,
+			QueryProp::Type(WindowType::Override) => Color::Red,
+			_ => return value,
+		};
+
+		value.color(color).to_string()
+	}
+
+	/// The properties of a window to output, in order and without the excluded ones
+	fn props<'a>(&'a self, window: &'a WindowDict) -> impl Iterator<Item = (WindowProp, QueryProp<'a>)> + 'a {
+		window.props().filter(|(key, _)| !self.exclude.contains(key))
+	}
+
+	/// The properties to output with their keys, prefixed with a label for the `both` context (e.g. `active.id`)
+	fn labeled_props<'a>(&'a self, window: &'a WindowDict, label: Option<&str>) -> Vec<(String, QueryProp<'a>)> {
+		let key = |key: WindowProp| match label {
+			Some(label) => format!("{label}.{key}"),
+			None => key.to_string(),
+		};
+
+		match self.property {
+			Some(QueryKey::Window(prop)) => vec![(key(prop), window.prop(prop))],
+			Some(QueryKey::Same) => vec![],
+			None => self.props(window).map(|(prop_key, prop)| (key(prop_key), prop)).collect(),
+		}
+	}
+
+	/// A window as a JSON value without the excluded properties, for the serialized formats
+	fn window_value(&self, window: &WindowDict) -> Result<serde_json::Value> {
+		let mut value = serde_json::to_value(window)?;
+
+		if let serde_json::Value::Object(map) = &mut value {
+			for key in &self.exclude {
+				map.remove(&key.to_string());
+			}
+		}
+
+		Ok(value)
+	}
+
+	fn format_both(&self, active: &WindowDict, pointer: &WindowDict) -> Result<String> {
+		let same = active.id == pointer.id;
+
+		if let Some(key @ QueryKey::Same) = self.property {
+			return self.format_prop(active, key, QueryProp::Same(same));
+		}
+
+		let active_props = self.labeled_props(active, Some("active"));
+		let pointer_props = self.labeled_props(pointer, Some("pointer"));
+
+		let same_prop = match self.property {
+			Some(_) => vec![],
+			None => vec![(QueryKey::Same.to_string(), QueryProp::Same(same))],
+		};
+
+		match self.format {
+			QueryFormat::Flat => {
+				if self.property.is_some() {
+					Ok(format!("{}\n{}", self.quote_prop(&active_props[0].1), self.quote_prop(&pointer_props[0].1)))
+				} else {
+					Ok(self.join_flat(&active_props) + "\n" + &self.join_flat(&pointer_props) + "\n" + &self.join_flat(&same_prop) + "\n")
 				}
-				QueryFormat::Dict => {
-					Ok([
-						format!("{} {}", "id:".bright_blue(), window.id),
-						format!("{} {}", "name:".bright_blue(), window.name),
-						format!("{} {}", "class:".bright_blue(), window.class),
-						format!("{} {}", "pid:".bright_blue(), window.pid),
-						format!("{} {}", "title:".bright_blue(), window.title),
-						format!("{} {}", "type:".bright_blue(), window.r#type),
-						format!("{} {}", "role:".bright_blue(), window.role),
-						format!("{} {}", "state:".bright_blue(), window.state),
-						format!("{} {}", "display:".bright_blue(), window.display),
-					].join("\n") + "\n")
+			}
+			QueryFormat::Dict => {
+				let output = self.join_dict(&active_props, "\n") + "\n" + &self.join_dict(&pointer_props, "\n");
+
+				if self.property.is_some() {
+					Ok(output)
+				} else {
+					Ok(output + "\n" + &self.join_dict(&same_prop, "") + "\n")
 				}
-				QueryFormat::TOML => {
-					toml::to_string(window).map_err(|e| e.into())
+			}
+			QueryFormat::Table => {
+				let header = ["", "active", "pointer"].map(String::from).to_vec();
+
+				let mut rows: Vec<_> = match self.property {
+					Some(QueryKey::Window(key)) => vec![vec![key.to_string(), active.prop(key).to_string(), pointer.prop(key).to_string()]],
+					_ => self.props(active).zip(self.props(pointer))
+						.map(|((key, active), (_, pointer))| vec![key.to_string(), active.to_string(), pointer.to_string()])
+						.collect(),
+				};
+
+				rows.extend(same_prop.iter().map(|(key, prop)| vec![key.clone(), prop.to_string()]));
+
+				let output = format_table(Some(header), rows);
+
+				if self.property.is_some() {
+					Ok(output.trim_end_matches('\n').to_string())
+				} else {
+					Ok(output)
+				}
+			}
+			QueryFormat::TOML => {
+				match self.property {
+					Some(QueryKey::Window(key)) => self.to_toml(&BothWindows { active: active.prop(key), pointer: pointer.prop(key), same: None }),
+					_ if self.exclude.is_empty() => self.to_toml(&BothWindows { active, pointer, same: Some(same) }),
+					_ => self.to_toml(&BothWindows { active: self.window_value(active)?, pointer: self.window_value(pointer)?, same: Some(same) }),
 				}
-				QueryFormat::JSON => {
-					serde_json::to_value(window)
-						.map(|v| to_colored_json_auto(&v).unwrap_or_default())
-						.map_err(|e| e.into())
+			}
+			QueryFormat::JSON => {
+				match self.property {
+					Some(QueryKey::Window(key)) => self.to_json(BothWindows { active: active.prop(key), pointer: pointer.prop(key), same: None }),
+					_ => self.to_json(BothWindows { active: self.window_value(active)?, pointer: self.window_value(pointer)?, same: Some(same) }),
 				}
-				QueryFormat::CSV => {
-					let mut wtr = csv::WriterBuilder::new()
-						.has_headers(self.first)
-						.from_writer(vec![]);
+			}
+			QueryFormat::CSV => {
+				let props: Vec<_> = active_props.into_iter().chain(pointer_props).chain(same_prop).collect();
 
-					wtr.serialize(window)?;
-					String::from_utf8(wtr.into_inner()?).map_err(|e| e.into())
+				let mut wtr = csv::WriterBuilder::new()
+					.delimiter(self.csv_delimiter)
+					.from_writer(vec![]);
+
+				if self.first {
+					wtr.write_record(props.iter().map(|(key, _)| key))?;
 				}
+
+				wtr.write_record(props.iter().map(|(_, prop)| prop.to_string()))?;
+				String::from_utf8(wtr.into_inner()?).map_err(|e| e.into())
+			}
+			QueryFormat::Waybar => {
+				Err(anyhow!("The waybar format only supports a single window context"))
 			}
 		}
 	}
 }
 
+/// Format rows of cells into aligned columns, with the key in the first column of each row
+pub fn format_table(header: Option<Vec<String>>, rows: Vec<Vec<String>>) -> String {
+	let widths: Vec<usize> = header.iter().chain(&rows)
+		.fold(Vec::new(), |mut widths, row| {
+			widths.resize(widths.len().max(row.len()), 0);
+
+			for (width, cell) in widths.iter_mut().zip(row) {
+				*width = (*width).max(cell.chars().count());
+			}
+
+			widths
+		});
+
+	// pad cells before coloring them, since the escape codes would count towards the width
+	let format_row = |row: &[String]| -> Vec<String> {
+		row.iter().zip(&widths).enumerate()
+			// don't pad the last column, to avoid trailing whitespace
+			.map(|(i, (cell, width))| if i + 1 == row.len() { cell.clone() } else { format!("{cell:<width$}") })
+			.collect()
+	};
+
+	let mut output = String::new();
+
+	if let Some(header) = &header {
+		output += &format!("{}\n", format_row(header).join("  ").bright_blue().bold());
+	}
+
+	for row in &rows {
+		let mut cells = format_row(row);
+
+		if let Some(key) = cells.first_mut() {
+			*key = key.bright_blue().to_string();
+		}
+
+		output += &format!("{}\n", cells.join("  "));
+	}
+
+	output
+}
+
 #[proxy(
 	interface = "org.wctx.Application",
 	default_service = "org.wctx",
@@ -246,13 +1108,265 @@ trait Windows {
 
 	#[zbus(property)]
 	fn pointer_window(&self) -> zbus::Result<DictMap>;
+
+	#[zbus(property)]
+	fn tracks_pointer(&self) -> zbus::Result<bool>;
+
+	#[zbus(signal)]
+	fn title_changed(&self, context: WindowContext, title: &str) -> zbus::Result<()>;
+
+	#[zbus(signal)]
+	fn state_changed(&self, context: WindowContext, state: &str) -> zbus::Result<()>;
+}
+
+#[proxy(
+	interface = "org.wctx.InputFocus",
+	default_service = "org.wctx",
+	default_path = "/"
+)]
+trait InputFocus {
+	fn focus_window(&self) -> zbus::Result<DictMap>;
 }
 
 pub async fn run(args: Args) -> Result<()> {
-	let connection = Connection::session().await?;
-	let application = ApplicationProxy::new(&connection).await?;
+	let args = if args.raw {
+		colored::control::set_override(false);
+		Args { output: OutputArgs { format: QueryFormat::Flat, ..args.output }, ..args }
+	} else {
+		args
+	};
+
+	if matches!((args.context, args.output.format), (Some(QueryContext::Both), QueryFormat::Waybar)) {
+		return Err(anyhow!("The waybar format only supports a single window context"));
+	}
+
+	if args.output.property == Some(QueryKey::Same) && !matches!(args.context, Some(QueryContext::Both)) {
+		return Err(anyhow!("The `{}` property is only available for the `both` context", QueryKey::Same));
+	}
+
+	if args.exit_code.is_some() && matches!(args.context, Some(QueryContext::Both)) && args.output.property != Some(QueryKey::Same) {
+		return Err(anyhow!("--exit-code only supports the `{}` property for the `both` context", QueryKey::Same));
+	}
+
+	if matches!(args.context, Some(QueryContext::Focus)) && (args.watch || args.standalone) {
+		return Err(anyhow!("The `focus` context can't be watched or queried in standalone mode"));
+	}
+
+	if args.standalone {
+		return run_standalone(args).await;
+	}
+
+	let connection = connect_bus(args.system_bus).await?;
+	let windows = connect_windows(&connection, args.connect_timeout).await?;
+	let window_arg = args.context.unwrap();
+
+	// older daemons don't have the property, but always track the pointer
+	if matches!(window_arg, QueryContext::Pointer | QueryContext::Both) && matches!(windows.tracks_pointer().await, Ok(false)) {
+		eprintln!("{} The daemon was started with {}, so the pointer window is always empty.", "Note:".bright_yellow().bold(), "--active-only".bold());
+	}
+
+	let (mut active_window, mut pointer_window) = read_windows(&windows, window_arg).await?;
+
+	let mut printer = Printer::new(&args);
+	printer.print(QueryWindow::new(window_arg, &active_window, &pointer_window))?;
+
+	if !args.watch {
+		return Ok(());
+	}
+
+	// a single property with its own signal only needs that signal, instead of every change to the window
+	let signal_prop = match args.output.property {
+		Some(QueryKey::Window(prop @ (WindowProp::Title | WindowProp::State))) if has_prop_signals(&connection).await => Some(prop),
+		_ => None,
+	};
+
+	let mut changes = if let Some(prop) = signal_prop {
+		let values = match prop {
+			WindowProp::Title => windows.receive_title_changed().await?
+				.filter_map(|signal| {
+					let args = signal.args().ok()?;
+					Some((args.context, args.title.to_string()))
+				})
+				.boxed_local(),
+			_ => windows.receive_state_changed().await?
+				.filter_map(|signal| {
+					let args = signal.args().ok()?;
+					Some((args.context, args.state.to_string()))
+				})
+				.boxed_local(),
+		};
+
+		values
+			.map(move |(context, value)| Ok::<_, anyhow::Error>((context, WindowUpdate::Prop(prop, value))))
+			.boxed_local()
+	} else {
+		let windows_changed = match window_arg {
+			QueryContext::Active => windows.receive_active_window_changed().await
+				.map(|changed| (WindowContext::Active, changed))
+				.boxed_local(),
+			QueryContext::Pointer => windows.receive_pointer_window_changed().await
+				.map(|changed| (WindowContext::Pointer, changed))
+				.boxed_local(),
+			QueryContext::Both => windows.receive_active_window_changed().await
+				.map(|changed| (WindowContext::Active, changed))
+				.race(windows.receive_pointer_window_changed().await.map(|changed| (WindowContext::Pointer, changed)))
+				.boxed_local(),
+		};
+
+		windows_changed
+			.then(|(context, changed)| async move {
+				let window = changed.get().await?.try_into()?;
+				Ok::<_, anyhow::Error>((context, WindowUpdate::Window(window)))
+			})
+			.boxed_local()
+	};
+
+	// the daemon losing its name means it stopped, and getting it back means it was restarted (e.g. after an upgrade)
+	let mut owner_changes = windows.inner().receive_owner_changed().await?;
+
+	// bursts of changes (e.g. a rapidly changing title) are only output once they settle
+	let mut buffer = Debouncer::new(Duration::from_millis(args.buffer));
+
+	loop {
+		tokio::select! {
+			change = changes.next() => {
+				let Some(change) = change else {
+					break;
+				};
+
+				let (context, update) = change?;
+
+				let window = match (window_arg, context) {
+					(QueryContext::Active | QueryContext::Both, WindowContext::Active) => &mut active_window,
+					(QueryContext::Pointer | QueryContext::Both, WindowContext::Pointer) => &mut pointer_window,
+					_ => continue,
+				};
+
+				match update {
+					WindowUpdate::Window(new_window) => *window = new_window,
+					WindowUpdate::Prop(prop, value) => window.update(prop, &value)?,
+				}
+
+				let window = QueryWindow::new(window_arg, &active_window, &pointer_window);
+
+				if args.buffer > 0 {
+					buffer.push(window);
+				} else {
+					printer.print(window)?;
+				}
+			}
+			Some(window) = buffer.next(), if args.buffer > 0 => {
+				printer.print(window)?;
+			}
+			Some(owner) = owner_changes.next() => {
+				if owner.is_some() {
+					// start over from the restarted daemon's current windows, since it only signals changes
+					connect_windows(&connection, args.connect_timeout).await?;
+					(active_window, pointer_window) = read_windows(&windows, window_arg).await?;
+					printer.print(QueryWindow::new(window_arg, &active_window, &pointer_window))?;
+				} else if args.no_reconnect {
+					return Err(anyhow!("The wctx daemon stopped"));
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Watch both the active and pointer windows, outputting each change tagged with its context
+pub async fn watch_all(args: WatchAllArgs) -> Result<()> {
+	if !matches!(args.output.format, QueryFormat::Flat | QueryFormat::Dict | QueryFormat::JSON) {
+		return Err(anyhow!("The {} format isn't supported for watch-all", args.output.format));
+	}
+
+	if args.output.property == Some(QueryKey::Same) {
+		return Err(anyhow!("The `{}` property is only available for the `both` context", QueryKey::Same));
+	}
+
+	let connection = connect_bus(args.system_bus).await?;
+	let windows = connect_windows(&connection, args.connect_timeout).await?;
+
+	let mut active_printer = Printer::with_output(&args.output, true, !args.no_dedupe).tagged("active");
+	let mut pointer_printer = Printer::with_output(&args.output, true, !args.no_dedupe).tagged("pointer");
 
-	let status = application.status().await.map_err(|_| {
+	let (active_window, pointer_window) = read_windows(&windows, QueryContext::Both).await?;
+
+	active_printer.print(QueryWindow::Single(active_window))?;
+	pointer_printer.print(QueryWindow::Single(pointer_window))?;
+
+	let mut stream = windows.receive_active_window_changed().await
+		.map(|changed| (WindowContext::Active, changed))
+		.race(windows.receive_pointer_window_changed().await.map(|changed| (WindowContext::Pointer, changed)))
+		.boxed_local();
+
+	while let Some((context, changed)) = stream.next().await {
+		let window = QueryWindow::Single(changed.get().await?.try_into()?);
+
+		match context {
+			WindowContext::Pointer => pointer_printer.print(window)?,
+			_ => active_printer.print(window)?,
+		}
+	}
+
+	Ok(())
+}
+
+/// Read the current windows for the context, leaving the other window empty
+async fn read_windows(windows: &WindowsProxy<'_>, context: QueryContext) -> Result<(WindowDict, WindowDict)> {
+	let mut active_window = WindowDict::default();
+	let mut pointer_window = WindowDict::default();
+
+	if matches!(context, QueryContext::Focus) {
+		return Ok((read_focus_window(windows.inner().connection()).await?, pointer_window));
+	}
+
+	if !matches!(context, QueryContext::Pointer) {
+		active_window = windows.active_window().await?.try_into()?;
+	}
+
+	if !matches!(context, QueryContext::Active) {
+		pointer_window = windows.pointer_window().await?.try_into()?;
+	}
+
+	Ok((active_window, pointer_window))
+}
+
+/// Read the window with the input focus, which is only served by providers that can determine it
+async fn read_focus_window(connection: &Connection) -> Result<WindowDict> {
+	let focus = InputFocusProxy::new(connection).await?;
+
+	match focus.focus_window().await {
+		Ok(window) => Ok(window.try_into()?),
+		Err(zbus::Error::MethodError(name, _, _)) if name.ends_with(".UnknownInterface") || name.ends_with(".UnknownMethod") => {
+			Err(anyhow!("The current window provider doesn't support the `focus` context"))
+		}
+		Err(zbus::Error::FDO(err)) if matches!(*err, zbus::fdo::Error::UnknownInterface(_) | zbus::fdo::Error::UnknownMethod(_)) => {
+			Err(anyhow!("The current window provider doesn't support the `focus` context"))
+		}
+		Err(err) => Err(err.into()),
+	}
+}
+
+/// Connect to the bus the daemon serves on
+pub async fn connect_bus(system_bus: bool) -> zbus::Result<Connection> {
+	if system_bus {
+		Connection::system().await
+	} else {
+		Connection::session().await
+	}
+}
+
+/// Connect to the daemon's windows, failing if the daemon reports a problem (e.g. a failed provider)
+async fn connect_windows(connection: &Connection, connect_timeout: u64) -> Result<WindowsProxy<'_>> {
+	connect_daemon(connection, connect_timeout).await?;
+
+	Ok(WindowsProxy::new(connection).await?)
+}
+
+/// Wait for the daemon to be available, failing if it reports a problem (e.g. a failed provider)
+pub async fn connect_daemon(connection: &Connection, connect_timeout: u64) -> Result<()> {
+	let status = get_status(connection, Duration::from_millis(connect_timeout)).await.map_err(|_| {
 		anyhow!(
 			"Couldn't connect to the wctx daemon. You might need to start it with \"{}\" or manually run \"{}\".",
 			"systemctl --user start wctx".bright_yellow().bold(),
@@ -261,31 +1375,80 @@ pub async fn run(args: Args) -> Result<()> {
 	})?;
 
 	if status != "" {
-		eprintln!("{} {}", "Daemon:".bright_red().bold(), status);
-		std::process::exit(126); // command cannot execute
+		return Err(ExitError::new(126, status).into()); // command cannot execute
+	}
+
+	Ok(())
+}
+
+/// Whether the daemon emits per-property signals, which older daemons don't
+async fn has_prop_signals(connection: &Connection) -> bool {
+	let introspect = async {
+		zbus::fdo::IntrospectableProxy::builder(connection)
+			.destination("org.wctx")?
+			.path("/")?
+			.build().await?
+			.introspect().await
+	};
+
+	introspect.await.is_ok_and(|xml: String| xml.contains("\"TitleChanged\""))
+}
+
+/// Get the daemon status, retrying with backoff until `timeout` in case the daemon is still starting (e.g. right after login)
+async fn get_status(connection: &Connection, timeout: Duration) -> zbus::Result<String> {
+	let start = Instant::now();
+	let mut backoff = Duration::from_millis(50);
+
+	loop {
+		let result = async {
+			ApplicationProxy::new(connection).await?.status().await
+		}.await;
+
+		match result {
+			Err(_) if start.elapsed() + backoff <= timeout => {
+				tokio::time::sleep(backoff).await;
+				backoff = (backoff * 2).min(Duration::from_millis(500));
+			}
+			result => return result,
+		}
 	}
+}
+
+async fn run_standalone(args: Args) -> Result<()> {
+	let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+	let provider_task = tokio::spawn(crate::daemon::standalone(tx));
 
-	let windows = WindowsProxy::new(&connection).await?;
 	let window_arg = args.context.unwrap();
+	let mut printer = Printer::new(&args);
 
-	let window = match window_arg {
-		QueryContext::Active => windows.active_window().await,
-		QueryContext::Pointer => windows.pointer_window().await,
-	}?;
+	let mut active_window = None;
+	let mut pointer_window = None;
 
-	let mut printer = Printer::new(args.property, args.format, args.watch);
-	printer.print(window.try_into()?);
+	while let Some((context, window)) = rx.recv().await {
+		match context {
+			WindowContext::Active => active_window = Some(window),
+			WindowContext::Pointer => pointer_window = Some(window),
+			WindowContext::Both => {
+				active_window = Some(window.clone());
+				pointer_window = Some(window);
+			}
+		}
 
-	if args.watch {
-		let mut stream = match window_arg {
-			QueryContext::Active => windows.receive_active_window_changed().await,
-			QueryContext::Pointer => windows.receive_pointer_window_changed().await,
+		// wait until the provider has determined the initial windows for the context
+		let (active, pointer) = match (window_arg, &active_window, &pointer_window) {
+			(QueryContext::Active, Some(active), _) => (active, active),
+			(QueryContext::Pointer, _, Some(pointer)) => (pointer, pointer),
+			(QueryContext::Both, Some(active), Some(pointer)) => (active, pointer),
+			_ => continue,
 		};
 
-		while let Some(changed) = stream.next().await {
-			printer.print(changed.get().await?.try_into()?);
+		printer.print(QueryWindow::new(window_arg, active, pointer))?;
+
+		if !args.watch {
+			return Ok(());
 		}
 	}
 
-	Ok(())
+	// the provider only stops sending if it failed
+	provider_task.await?
 }
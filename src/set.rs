@@ -0,0 +1,46 @@
+use crate::types::*;
+use crate::query::connect_bus;
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use zbus::proxy;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct Args {
+	/// The window context to override
+	context: WindowContext,
+
+	/// The window to set, in the same JSON shape output by `--format json`
+	#[arg(long)]
+	json: String,
+
+	/// Connect to a daemon serving on the system bus instead of the session bus
+	#[arg(long)]
+	system_bus: bool,
+}
+
+#[proxy(
+	interface = "org.wctx.Windows",
+	default_service = "org.wctx",
+	default_path = "/"
+)]
+trait Windows {
+	fn override_window(&self, context: WindowContext, window: DictMap<'_>) -> zbus::Result<()>;
+}
+
+pub async fn run(args: Args) -> Result<()> {
+	let value = serde_json::from_str(&args.json).map_err(|err| anyhow!("Invalid window JSON: {}", err))?;
+	let window = WindowDict::from_json(value)?;
+
+	let connection = connect_bus(args.system_bus).await?;
+	let windows = WindowsProxy::new(&connection).await?;
+
+	windows.override_window(args.context, window.into()).await.map_err(|err| match err {
+		zbus::Error::MethodError(_, Some(msg), _) => anyhow!(msg),
+		zbus::Error::FDO(err) => anyhow!("{}", err),
+		_ => anyhow!(
+			"Couldn't connect to the wctx daemon. You might need to start it with \"{}\" or manually run \"{}\".",
+			"systemctl --user start wctx".bright_yellow().bold(),
+			"wctx daemon".bright_yellow().bold(),
+		),
+	})
+}
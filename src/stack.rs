@@ -0,0 +1,58 @@
+use crate::query::connect_bus;
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use zbus::proxy;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct Args {
+	/// The window to restack, as output by the `id` property [default: the active window]
+	id: Option<String>,
+
+	/// Connect to a daemon serving on the system bus instead of the session bus
+	#[arg(long)]
+	system_bus: bool,
+}
+
+#[proxy(
+	interface = "org.wctx.Stacking",
+	default_service = "org.wctx",
+	default_path = "/"
+)]
+trait Stacking {
+	fn raise_window(&self, id: &str) -> zbus::Result<()>;
+
+	fn lower_window(&self, id: &str) -> zbus::Result<()>;
+}
+
+pub async fn raise(args: Args) -> Result<()> {
+	let connection = connect_bus(args.system_bus).await?;
+	let stacking = StackingProxy::new(&connection).await?;
+
+	stacking.raise_window(args.id.as_deref().unwrap_or_default()).await.map_err(map_error)
+}
+
+pub async fn lower(args: Args) -> Result<()> {
+	let connection = connect_bus(args.system_bus).await?;
+	let stacking = StackingProxy::new(&connection).await?;
+
+	stacking.lower_window(args.id.as_deref().unwrap_or_default()).await.map_err(map_error)
+}
+
+fn map_error(err: zbus::Error) -> anyhow::Error {
+	match err {
+		// the interface is only served by providers that can restack windows
+		zbus::Error::MethodError(name, _, _) if name.ends_with(".UnknownInterface") || name.ends_with(".UnknownMethod") => {
+			anyhow!("The current window provider doesn't support restacking windows")
+		}
+		zbus::Error::FDO(err) if matches!(*err, zbus::fdo::Error::UnknownInterface(_) | zbus::fdo::Error::UnknownMethod(_)) => {
+			anyhow!("The current window provider doesn't support restacking windows")
+		}
+		zbus::Error::MethodError(_, Some(msg), _) => anyhow!(msg),
+		zbus::Error::FDO(err) => anyhow!("{}", err),
+		_ => anyhow!(
+			"Couldn't connect to the wctx daemon. You might need to start it with \"{}\" or manually run \"{}\".",
+			"systemctl --user start wctx".bright_yellow().bold(),
+			"wctx daemon".bright_yellow().bold(),
+		),
+	}
+}
@@ -8,7 +8,7 @@ use zbus::zvariant::{Type, Value};
 
 pub type DictMap<'a> = HashMap<String, Value<'a>>;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize, Type)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize, Type, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
 #[zvariant(signature = "s")]
 pub enum WindowContext {
@@ -17,27 +17,72 @@ pub enum WindowContext {
 	Pointer,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize, Type, clap::ValueEnum, strum::Display, strum::AsRefStr)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize, Type, clap::ValueEnum, strum::Display, strum::AsRefStr, strum::IntoStaticStr)]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
 #[zvariant(signature = "s")]
 pub enum WindowProp {
 	ID,
+	#[value(alias = "n")]
 	Name,
+	#[value(alias = "c", alias = "cls")]
 	Class,
+	/// Icon name from the application's desktop entry, falling back to the class
+	Icon,
 	PID,
+	/// Short process name, from `/proc/<pid>/comm`
+	Comm,
+	#[value(alias = "t")]
 	Title,
 	Type,
 	Role,
+	/// Id of the group leader window that an application's windows share (X11 only), or empty if none
+	Leader,
+	#[value(alias = "s")]
 	State,
+	/// All EWMH states of the window, comma-separated (e.g. `SKIP_TASKBAR,ABOVE`) (X11 only)
+	States,
+	/// Whether the window manager draws the titlebar and borders, rather than the client (CSD) or nothing
+	Decorated,
+	/// Whether the window accepts keyboard focus, from the input hint and `WM_TAKE_FOCUS` (X11 only)
+	#[serde(rename = "accepts_focus")]
+	#[strum(to_string = "accepts_focus")]
+	#[value(name = "accepts_focus", alias = "accepts-focus")]
+	AcceptsFocus,
+	#[value(alias = "d")]
 	Display,
+	#[serde(rename = "display_index")]
+	#[strum(to_string = "display_index")]
+	#[value(name = "display_index", alias = "display-index")]
+	DisplayIndex,
+	/// Horizontal position of the window frame, relative to the whole screen
+	X,
+	/// Vertical position of the window frame, relative to the whole screen
+	Y,
+	Width,
+	Height,
+	/// Virtual desktop (workspace) name, comma-separated if on several, or empty if on all of them
+	#[value(alias = "w", alias = "ws")]
+	Workspace,
+	/// Position in the stacking order from the bottom, or -1 if unknown
+	Stack,
+	/// X server time of the last user interaction with the window (X11 only), or 0 if unknown
+	#[serde(rename = "user_time")]
+	#[strum(to_string = "user_time")]
+	#[value(name = "user_time", alias = "user-time")]
+	UserTime,
+	/// Color depth of the window in bits, e.g. 32 for windows with an alpha channel (X11 only), or 0 if unknown
+	Depth,
+	/// Id of the window's X11 visual, or 0 if unknown
+	Visual,
+	/// Seconds since the window became the active (or pointer) window
+	Age,
 }
 
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, strum::EnumString, strum::Display, strum::AsRefStr, strum::VariantNames)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+/// Serialized with its `Display` value (e.g. `DROPDOWN_MENU`) so that every output format and D-Bus agree
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, strum::EnumString, strum::Display, strum::AsRefStr, strum::VariantNames)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE", ascii_case_insensitive)]
 pub enum WindowType {
-	#[serde(rename = "")]
 	#[strum(to_string = "")]
 	None,
 	#[default]
@@ -45,7 +90,6 @@ pub enum WindowType {
 	Combo,
 	Desktop,
 	Dialog,
-	#[serde(rename = "DND")]
 	DND,
 	Dock,
 	DropdownMenu,
@@ -59,30 +103,51 @@ pub enum WindowType {
 	Override, // GNOME non-standard
 }
 
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, strum::EnumString, strum::Display, strum::AsRefStr, strum::VariantNames)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+/// Serialized with its `Display` value (e.g. `MAXIMIZED`), like `WindowType`
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, strum::EnumString, strum::Display, strum::AsRefStr, strum::VariantNames)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE", ascii_case_insensitive)]
 pub enum WindowState {
-	#[serde(rename = "")]
 	#[strum(to_string = "")]
 	None,
 	#[default]
 	Normal,
 	Maximized,
 	Fullscreen,
+	Tiled,
+	Floating,
+	/// Rolled up to its titlebar
+	Shaded,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct WindowDict {
 	pub id: String,
 	pub name: String,
 	pub class: String,
+	pub icon: String,
 	pub pid: u32,
+	pub comm: String,
 	pub title: String,
 	pub r#type: WindowType,
 	pub role: String,
+	pub leader: String,
 	pub state: WindowState,
+	pub states: String,
+	pub decorated: bool,
+	pub accepts_focus: bool,
 	pub display: String,
+	pub display_index: u32,
+	pub x: i32,
+	pub y: i32,
+	pub width: u32,
+	pub height: u32,
+	pub workspace: String,
+	pub stack: i32,
+	pub user_time: u32,
+	pub depth: u32,
+	pub visual: u32,
+	pub age: u32,
 }
 
 impl WindowDict {
@@ -90,23 +155,57 @@ impl WindowDict {
 		id: &str,
 		name: &str,
 		class: &str,
+		icon: &str,
 		pid: u32,
+		comm: &str,
 		title: &str,
 		r#type: WindowType,
 		role: &str,
+		leader: &str,
 		state: WindowState,
+		states: &str,
+		decorated: bool,
+		accepts_focus: bool,
 		display: &str,
+		display_index: u32,
+		x: i32,
+		y: i32,
+		width: u32,
+		height: u32,
+		workspace: &str,
+		stack: i32,
+		user_time: u32,
+		depth: u32,
+		visual: u32,
 	) -> Self {
 		Self {
 			id: id.into(),
 			name: name.into(),
 			class: class.into(),
+			icon: icon.into(),
 			pid,
+			comm: comm.into(),
 			title: title.into(),
 			r#type,
 			role: role.into(),
+			leader: leader.into(),
 			state,
+			states: states.into(),
+			decorated,
+			accepts_focus,
 			display: display.into(),
+			display_index,
+			x,
+			y,
+			width,
+			height,
+			workspace: workspace.into(),
+			stack,
+			user_time,
+			depth,
+			visual,
+			// tracked by the daemon rather than providers
+			age: 0,
 		}
 	}
 
@@ -115,26 +214,65 @@ impl WindowDict {
 			("id".to_string(), Value::from(&self.id)),
 			("name".to_string(), Value::from(&self.name)),
 			("class".to_string(), Value::from(&self.class)),
+			("icon".to_string(), Value::from(&self.icon)),
 			("pid".to_string(), Value::from(&self.pid)),
+			("comm".to_string(), Value::from(&self.comm)),
 			("title".to_string(), Value::from(&self.title)),
 			("type".to_string(), Value::from(self.r#type.to_string())),
 			("role".to_string(), Value::from(&self.role)),
+			("leader".to_string(), Value::from(&self.leader)),
 			("state".to_string(), Value::from(self.state.to_string())),
+			("states".to_string(), Value::from(&self.states)),
+			("decorated".to_string(), Value::from(self.decorated)),
+			("accepts_focus".to_string(), Value::from(self.accepts_focus)),
 			("display".to_string(), Value::from(&self.display)),
+			("display_index".to_string(), Value::from(&self.display_index)),
+			("x".to_string(), Value::from(&self.x)),
+			("y".to_string(), Value::from(&self.y)),
+			("width".to_string(), Value::from(&self.width)),
+			("height".to_string(), Value::from(&self.height)),
+			("workspace".to_string(), Value::from(&self.workspace)),
+			("stack".to_string(), Value::from(&self.stack)),
+			("user_time".to_string(), Value::from(&self.user_time)),
+			("depth".to_string(), Value::from(&self.depth)),
+			("visual".to_string(), Value::from(&self.visual)),
+			("age".to_string(), Value::from(&self.age)),
 		])
 	}
 
+	/// Create a window from the JSON representation output by `--format json`, with missing properties left empty
+	pub fn from_json(value: serde_json::Value) -> fdo::Result<Self> {
+		serde_json::from_value(value).map_err(|err| fdo::Error::InvalidArgs(format!("Invalid window: {}", err)))
+	}
+
 	pub fn update(&mut self, key: WindowProp, value: &str) -> fdo::Result<()> {
 		match key {
 			WindowProp::ID => self.id = value.into(),
 			WindowProp::Name => self.name = value.into(),
 			WindowProp::Class => self.class = value.into(),
+			WindowProp::Icon => self.icon = value.into(),
 			WindowProp::PID => self.pid = parse_int_string(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))?,
+			WindowProp::Comm => self.comm = value.into(),
 			WindowProp::Title => self.title = value.into(),
 			WindowProp::Type => self.r#type = WindowType::from_str(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected valid value for `{}` (\"\"{})", key, WindowType::VARIANTS.join(", "))))?,
 			WindowProp::Role => self.role = value.into(),
+			WindowProp::Leader => self.leader = value.into(),
 			WindowProp::State => self.state = WindowState::from_str(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected valid value for `{}` (\"\"{})", key, WindowState::VARIANTS.join(", "))))?,
+			WindowProp::States => self.states = value.into(),
+			WindowProp::Decorated => self.decorated = value.parse().map_err(|_| fdo::Error::InvalidArgs(format!("Expected boolean value for `{}`", key)))?,
+			WindowProp::AcceptsFocus => self.accepts_focus = value.parse().map_err(|_| fdo::Error::InvalidArgs(format!("Expected boolean value for `{}`", key)))?,
 			WindowProp::Display => self.display = value.into(),
+			WindowProp::DisplayIndex => self.display_index = parse_int_string(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))?,
+			WindowProp::X => self.x = parse_signed_int_string(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))?,
+			WindowProp::Y => self.y = parse_signed_int_string(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))?,
+			WindowProp::Width => self.width = parse_int_string(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))?,
+			WindowProp::Height => self.height = parse_int_string(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))?,
+			WindowProp::Workspace => self.workspace = value.into(),
+			WindowProp::Stack => self.stack = if value == "" { -1 } else { value.parse().map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))? },
+			WindowProp::UserTime => self.user_time = parse_int_string(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))?,
+			WindowProp::Depth => self.depth = parse_int_string(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))?,
+			WindowProp::Visual => self.visual = parse_int_string(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))?,
+			WindowProp::Age => self.age = parse_int_string(value).map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key)))?,
 		}
 
 		Ok(())
@@ -147,12 +285,31 @@ impl Default for WindowDict {
 			id: Default::default(),
 			name: Default::default(),
 			class: Default::default(),
+			icon: Default::default(),
 			pid: Default::default(),
+			comm: Default::default(),
 			title: Default::default(),
 			r#type: WindowType::None,
 			role: Default::default(),
+			leader: Default::default(),
 			state: WindowState::None,
+			states: Default::default(),
+			// server-side decorations are the norm when the provider doesn't know
+			decorated: true,
+			// most windows accept focus, so it's assumed when the provider doesn't know
+			accepts_focus: true,
 			display: Default::default(),
+			display_index: Default::default(),
+			x: Default::default(),
+			y: Default::default(),
+			width: Default::default(),
+			height: Default::default(),
+			workspace: Default::default(),
+			stack: -1,
+			user_time: Default::default(),
+			depth: Default::default(),
+			visual: Default::default(),
+			age: Default::default(),
 		}
 	}
 }
@@ -165,12 +322,32 @@ impl TryFrom<DictMap<'_>> for WindowDict {
 			id: map.extract("id")?,
 			name: map.extract("name")?,
 			class: map.extract("class")?,
+			// resolved by the daemon if the provider doesn't know the desktop entry
+			icon: if map.contains_key("icon") { map.extract("icon")? } else { Default::default() },
 			pid: map.extract("pid")?,
+			comm: map.extract("comm")?,
 			title: map.extract("title")?,
 			r#type: map.extract("type")?,
 			role: map.extract("role")?,
+			leader: if map.contains_key("leader") { map.extract("leader")? } else { Default::default() },
 			state: map.extract("state")?,
+			states: if map.contains_key("states") { map.extract("states")? } else { Default::default() },
+			decorated: if map.contains_key("decorated") { map.extract("decorated")? } else { true },
+			accepts_focus: if map.contains_key("accepts_focus") { map.extract("accepts_focus")? } else { true },
 			display: map.extract("display")?,
+			display_index: map.extract("display_index")?,
+			x: map.extract("x")?,
+			y: map.extract("y")?,
+			width: map.extract("width")?,
+			height: map.extract("height")?,
+			workspace: map.extract("workspace")?,
+			// providers without stacking info don't send it
+			stack: if map.contains_key("stack") { map.extract("stack")? } else { -1 },
+			user_time: if map.contains_key("user_time") { map.extract("user_time")? } else { 0 },
+			depth: if map.contains_key("depth") { map.extract("depth")? } else { 0 },
+			visual: if map.contains_key("visual") { map.extract("visual")? } else { 0 },
+			// only set by the daemon
+			age: if map.contains_key("age") { map.extract("age")? } else { 0 },
 		})
 	}
 }
@@ -181,12 +358,81 @@ impl<'a> Into<DictMap<'a>> for WindowDict {
 			("id".to_string(), Value::from(self.id)),
 			("name".to_string(), Value::from(self.name)),
 			("class".to_string(), Value::from(self.class)),
+			("icon".to_string(), Value::from(self.icon)),
 			("pid".to_string(), Value::from(self.pid)),
+			("comm".to_string(), Value::from(self.comm)),
 			("title".to_string(), Value::from(self.title)),
 			("type".to_string(), Value::from(self.r#type.to_string())),
 			("role".to_string(), Value::from(self.role)),
+			("leader".to_string(), Value::from(self.leader)),
 			("state".to_string(), Value::from(self.state.to_string())),
+			("states".to_string(), Value::from(self.states)),
+			("decorated".to_string(), Value::from(self.decorated)),
+			("accepts_focus".to_string(), Value::from(self.accepts_focus)),
 			("display".to_string(), Value::from(self.display)),
+			("display_index".to_string(), Value::from(self.display_index)),
+			("x".to_string(), Value::from(self.x)),
+			("y".to_string(), Value::from(self.y)),
+			("width".to_string(), Value::from(self.width)),
+			("height".to_string(), Value::from(self.height)),
+			("workspace".to_string(), Value::from(self.workspace)),
+			("stack".to_string(), Value::from(self.stack)),
+			("user_time".to_string(), Value::from(self.user_time)),
+			("depth".to_string(), Value::from(self.depth)),
+			("visual".to_string(), Value::from(self.visual)),
+			("age".to_string(), Value::from(self.age)),
+		])
+	}
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DisplayDict {
+	pub name: String,
+	pub x: i32,
+	pub y: i32,
+	pub width: u32,
+	pub height: u32,
+	pub primary: bool,
+	/// Scale factor, or 1.0 if the provider doesn't know it
+	pub scale: f64,
+	/// Physical DPI, or 0 if the physical size is unknown
+	pub dpi: u32,
+	/// Refresh rate of the current mode in Hz, or 0 if unknown
+	pub refresh_rate: f64,
+}
+
+impl TryFrom<DictMap<'_>> for DisplayDict {
+	type Error = fdo::Error;
+
+	fn try_from(map: DictMap) -> Result<Self, Self::Error> {
+		let scale: f64 = map.extract("scale")?;
+
+		Ok(Self {
+			name: map.extract("name")?,
+			x: map.extract("x")?,
+			y: map.extract("y")?,
+			width: map.extract("width")?,
+			height: map.extract("height")?,
+			primary: map.extract("primary")?,
+			scale: if scale > 0.0 { scale } else { 1.0 },
+			dpi: map.extract("dpi")?,
+			refresh_rate: map.extract("refresh_rate")?,
+		})
+	}
+}
+
+impl<'a> Into<DictMap<'a>> for DisplayDict {
+	fn into(self) -> DictMap<'a> {
+		HashMap::from([
+			("name".to_string(), Value::from(self.name)),
+			("x".to_string(), Value::from(self.x)),
+			("y".to_string(), Value::from(self.y)),
+			("width".to_string(), Value::from(self.width)),
+			("height".to_string(), Value::from(self.height)),
+			("primary".to_string(), Value::from(self.primary)),
+			("scale".to_string(), Value::from(self.scale)),
+			("dpi".to_string(), Value::from(self.dpi)),
+			("refresh_rate".to_string(), Value::from(self.refresh_rate)),
 		])
 	}
 }
@@ -218,6 +464,40 @@ impl ValueExt<u32> for DictMap<'_> {
 	}
 }
 
+impl ValueExt<i32> for DictMap<'_> {
+	fn extract(&self, key: &str) -> fdo::Result<i32> {
+		match self.get(key) {
+			Some(v) => match u32::try_from(v) {
+				Ok(v) => i32::try_from(v)
+					.map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key))),
+				Err(_) => i32::try_from(v)
+					.map_err(|_| fdo::Error::InvalidArgs(format!("Expected integer value for `{}`", key))),
+			},
+			None => Ok(i32::default()),
+		}
+	}
+}
+
+impl ValueExt<bool> for DictMap<'_> {
+	fn extract(&self, key: &str) -> fdo::Result<bool> {
+		match self.get(key) {
+			Some(v) => bool::try_from(v)
+				.map_err(|_| fdo::Error::InvalidArgs(format!("Expected boolean value for `{}`", key))),
+			None => Ok(bool::default()),
+		}
+	}
+}
+
+impl ValueExt<f64> for DictMap<'_> {
+	fn extract(&self, key: &str) -> fdo::Result<f64> {
+		match self.get(key) {
+			Some(v) => f64::try_from(v)
+				.map_err(|_| fdo::Error::InvalidArgs(format!("Expected number value for `{}`", key))),
+			None => Ok(f64::default()),
+		}
+	}
+}
+
 fn parse_int_string(value: &str) -> Result<u32, ParseIntError> {
 	if value == "" {
 		Ok(0)
@@ -226,6 +506,14 @@ fn parse_int_string(value: &str) -> Result<u32, ParseIntError> {
 	}
 }
 
+fn parse_signed_int_string(value: &str) -> Result<i32, ParseIntError> {
+	if value == "" {
+		Ok(0)
+	} else {
+		value.parse::<i32>()
+	}
+}
+
 macro_rules! impl_from_str_enum {
     ($type:ty) => {
         impl ValueExt<$type> for DictMap<'_> {
@@ -240,6 +528,52 @@ macro_rules! impl_from_str_enum {
 impl_from_str_enum!(WindowType);
 impl_from_str_enum!(WindowState);
 
+/// An error that makes the process exit with a specific code, which `main` reports like any other error
+#[derive(Debug)]
+pub struct ExitError {
+	pub code: i32,
+	/// Empty to exit without printing anything
+	pub message: String,
+}
+
+impl ExitError {
+	pub fn new(code: i32, message: impl Into<String>) -> Self {
+		Self { code, message: message.into() }
+	}
+
+	pub fn silent(code: i32) -> Self {
+		Self::new(code, "")
+	}
+}
+
+impl std::fmt::Display for ExitError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.message)
+	}
+}
+
+impl std::error::Error for ExitError {}
+
+macro_rules! impl_serde_as_str {
+    ($type:ty) => {
+        impl Serialize for $type {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_ref())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                <$type>::from_str(&s).map_err(|_| serde::de::Error::custom(format!("expected one of: \"\", {}", <$type>::VARIANTS[1..].join(", "))))
+            }
+        }
+    };
+}
+
+impl_serde_as_str!(WindowType);
+impl_serde_as_str!(WindowState);
+
 macro_rules! concurrent {
     ($(let $var:ident = $expr:expr),* $(,)?) => {
         let (